@@ -12,11 +12,13 @@ class Row(NamedTuple):
     operation: str
     collection: str
     nanoseconds: int
+    page_size: Optional[int] = None
+    cache_state: Optional[str] = None
 
     @staticmethod
     def parse(row_strings: List[str]) -> 'Row':
-        if len(row_strings) != 5:
-            raise BadRowFormat(row_strings, 'must have 5 elements')
+        if len(row_strings) not in (5, 6, 7):
+            raise BadRowFormat(row_strings, 'must have 5 to 7 elements')
 
         try:
             size = int(row_strings[1])
@@ -30,18 +32,36 @@ class Row(NamedTuple):
                 row_strings,
                 f'nanoseconds parse error because {error}')
 
+        page_size = None
+        if len(row_strings) >= 6:
+            try:
+                page_size = int(row_strings[5])
+            except ValueError as error:
+                raise BadRowFormat(
+                    row_strings,
+                    f'page size parse error because {error}')
+
+        cache_state = None
+        if len(row_strings) == 7 and row_strings[6]:
+            cache_state = row_strings[6]
+
         return Row(
             mode=row_strings[0],
             size=size,
             operation=row_strings[2],
             collection=row_strings[3],
-            nanoseconds=nanoseconds)
+            nanoseconds=nanoseconds,
+            page_size=page_size,
+            cache_state=cache_state)
 
 def parse_rows(path: str) -> List[Row]:
     rows: List[Row] = []
     with open(path) as file:
         reader = csv.reader(file)
         for row in reader:
+            # Failed cells of isolated runs have no time.
+            if len(row) >= 5 and not row[4]:
+                continue
             rows.append(Row.parse(row))
     return rows
 
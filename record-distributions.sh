@@ -0,0 +1,16 @@
+#!/usr/bin/env sh
+
+set -e
+
+rm -f output-distributions.csv
+
+cargo build --release
+time cargo run --release -- -o output-distributions.csv \
+    -m release-random --distribution random
+# Unbalanced trees take quadratic time to create from sorted inputs.
+for distribution in sorted nearly-sorted
+do
+    time cargo run --release -- -o output-distributions.csv \
+        -m "release-$distribution" --distribution "$distribution" \
+        --max-size 1048576
+done
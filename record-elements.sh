@@ -0,0 +1,12 @@
+#!/usr/bin/env sh
+
+set -e
+
+rm -f output-elements.csv
+
+cargo build --release
+for element in u32 u64 u128 record
+do
+    time cargo run --release -- -o output-elements.csv \
+        -m "release-$element" --element "$element"
+done
@@ -0,0 +1,15 @@
+#!/usr/bin/env sh
+
+set -e
+
+rm -f output-madvise.csv
+
+cargo build --release
+time cargo run --release -- -o output-madvise.csv -m release-mmap --mmap
+for advice in sequential random will-need huge-page no-huge-page
+do
+    time cargo run --release -- -o output-madvise.csv \
+        -m "release-mmap-$advice" --mmap --madvise "$advice"
+done
+time cargo run --release -- -o output-madvise.csv \
+    -m release-mmap-populate --mmap --map-populate
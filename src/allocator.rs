@@ -0,0 +1,323 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    mem,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering},
+};
+
+const SIZE_CLASSES: usize = usize::BITS as usize;
+
+// Every block ends with a stamp holding the allocator clock at which it was
+// allocated, or zero if it was allocated while not counting.
+const STAMP_SIZE: usize = mem::size_of::<u64>();
+
+// Only counts when built with the count-allocations feature, which makes it
+// the global allocator.
+#[cfg_attr(feature = "count-allocations", global_allocator)]
+pub static ALLOCATOR: CountingAllocator = CountingAllocator::new();
+
+pub fn is_installed() -> bool {
+    cfg!(feature = "count-allocations")
+}
+
+// Bytes added after every heap block, which are not the collection's.
+pub fn stamp_size() -> usize {
+    if is_installed() {
+        STAMP_SIZE
+    } else {
+        0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AllocationStats {
+    pub allocations: u64,
+    pub reallocations: u64,
+    pub frees: u64,
+    pub allocated_bytes: u64,
+    pub freed_bytes: u64,
+    // Relative to the live bytes when counting started.
+    pub peak_live_bytes: i64,
+    size_classes: [u64; SIZE_CLASSES],
+    class_frees: [u64; SIZE_CLASSES],
+    class_lifetimes: [u64; SIZE_CLASSES],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SizeClass {
+    // Largest size in the class.
+    pub size: usize,
+    // Allocations and the new size of reallocations.
+    pub count: u64,
+    // Blocks both allocated and freed while counting.
+    pub frees: u64,
+    // Allocator operations between allocating and freeing those blocks.
+    pub mean_lifetime: f64,
+}
+
+impl AllocationStats {
+    pub fn size_classes(&self) -> impl Iterator<Item = SizeClass> + '_ {
+        (0 .. SIZE_CLASSES)
+            .filter(|&class| {
+                self.size_classes[class] > 0 || self.class_frees[class] > 0
+            })
+            .map(|class| {
+                let frees = self.class_frees[class];
+                let mean_lifetime = if frees == 0 {
+                    0.0
+                } else {
+                    self.class_lifetimes[class] as f64 / frees as f64
+                };
+                SizeClass {
+                    size: 1 << class,
+                    count: self.size_classes[class],
+                    frees,
+                    mean_lifetime,
+                }
+            })
+    }
+}
+
+#[derive(Debug)]
+pub struct CountingAllocator {
+    counting: AtomicBool,
+    allocations: AtomicU64,
+    reallocations: AtomicU64,
+    frees: AtomicU64,
+    allocated_bytes: AtomicU64,
+    freed_bytes: AtomicU64,
+    live_bytes: AtomicI64,
+    peak_live_bytes: AtomicI64,
+    size_classes: [AtomicU64; SIZE_CLASSES],
+    class_frees: [AtomicU64; SIZE_CLASSES],
+    class_lifetimes: [AtomicU64; SIZE_CLASSES],
+    // Counted allocator operations, never reset.
+    clock: AtomicU64,
+    started_at: AtomicU64,
+}
+
+impl CountingAllocator {
+    pub const fn new() -> Self {
+        Self {
+            counting: AtomicBool::new(false),
+            allocations: AtomicU64::new(0),
+            reallocations: AtomicU64::new(0),
+            frees: AtomicU64::new(0),
+            allocated_bytes: AtomicU64::new(0),
+            freed_bytes: AtomicU64::new(0),
+            live_bytes: AtomicI64::new(0),
+            peak_live_bytes: AtomicI64::new(0),
+            size_classes: [const { AtomicU64::new(0) }; SIZE_CLASSES],
+            class_frees: [const { AtomicU64::new(0) }; SIZE_CLASSES],
+            class_lifetimes: [const { AtomicU64::new(0) }; SIZE_CLASSES],
+            clock: AtomicU64::new(0),
+            started_at: AtomicU64::new(0),
+        }
+    }
+
+    pub fn start(&self) {
+        let counters = [
+            &self.allocations,
+            &self.reallocations,
+            &self.frees,
+            &self.allocated_bytes,
+            &self.freed_bytes,
+        ];
+        let classes = self
+            .size_classes
+            .iter()
+            .chain(&self.class_frees)
+            .chain(&self.class_lifetimes);
+        for counter in counters.into_iter().chain(classes) {
+            counter.store(0, Ordering::Relaxed);
+        }
+        self.live_bytes.store(0, Ordering::Relaxed);
+        self.peak_live_bytes.store(0, Ordering::Relaxed);
+        // Blocks stamped before this are not counted when freed.
+        let started_at = self.clock.fetch_add(1, Ordering::Relaxed) + 1;
+        self.started_at.store(started_at, Ordering::Relaxed);
+        self.counting.store(true, Ordering::SeqCst);
+    }
+
+    pub fn stop(&self) -> AllocationStats {
+        self.counting.store(false, Ordering::SeqCst);
+        let load = |counters: &[AtomicU64; SIZE_CLASSES]| {
+            let mut counts = [0; SIZE_CLASSES];
+            for (count, counter) in counts.iter_mut().zip(counters) {
+                *count = counter.load(Ordering::Relaxed);
+            }
+            counts
+        };
+        AllocationStats {
+            allocations: self.allocations.load(Ordering::Relaxed),
+            reallocations: self.reallocations.load(Ordering::Relaxed),
+            frees: self.frees.load(Ordering::Relaxed),
+            allocated_bytes: self.allocated_bytes.load(Ordering::Relaxed),
+            freed_bytes: self.freed_bytes.load(Ordering::Relaxed),
+            peak_live_bytes: self.peak_live_bytes.load(Ordering::Relaxed),
+            size_classes: load(&self.size_classes),
+            class_frees: load(&self.class_frees),
+            class_lifetimes: load(&self.class_lifetimes),
+        }
+    }
+
+    fn is_counting(&self) -> bool {
+        self.counting.load(Ordering::Relaxed)
+    }
+
+    unsafe fn count_alloc(&self, pointer: *mut u8, layout: Layout) {
+        let stamp = self.tick();
+        if stamp != 0 {
+            self.allocations.fetch_add(1, Ordering::Relaxed);
+            self.grow(layout.size(), 0);
+        }
+        pointer.add(layout.size()).cast::<u64>().write_unaligned(stamp);
+    }
+
+    // Advances the clock if counting, returning the stamp of an operation.
+    fn tick(&self) -> u64 {
+        if self.is_counting() {
+            self.clock.fetch_add(1, Ordering::Relaxed)
+        } else {
+            0
+        }
+    }
+
+    fn grow(&self, new_size: usize, old_size: usize) {
+        let class = size_class(new_size);
+        self.size_classes[class].fetch_add(1, Ordering::Relaxed);
+        self.allocated_bytes.fetch_add(new_size as u64, Ordering::Relaxed);
+        self.freed_bytes.fetch_add(old_size as u64, Ordering::Relaxed);
+        let delta = new_size as i64 - old_size as i64;
+        let live = self.live_bytes.fetch_add(delta, Ordering::Relaxed) + delta;
+        self.peak_live_bytes.fetch_max(live, Ordering::Relaxed);
+    }
+}
+
+impl Default for CountingAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let Some(outer) = stamped(layout, layout.size()) else {
+            return ptr::null_mut();
+        };
+        let pointer = System.alloc(outer);
+        if !pointer.is_null() {
+            self.count_alloc(pointer, layout);
+        }
+        pointer
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let Some(outer) = stamped(layout, layout.size()) else {
+            return ptr::null_mut();
+        };
+        let pointer = System.alloc_zeroed(outer);
+        if !pointer.is_null() {
+            self.count_alloc(pointer, layout);
+        }
+        pointer
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        let outer = stamped(layout, layout.size()).unwrap();
+        let allocated_at = read_stamp(pointer, layout.size());
+        System.dealloc(pointer, outer);
+        let now = self.tick();
+        if now != 0 {
+            self.frees.fetch_add(1, Ordering::Relaxed);
+            self.freed_bytes
+                .fetch_add(layout.size() as u64, Ordering::Relaxed);
+            self.live_bytes
+                .fetch_sub(layout.size() as i64, Ordering::Relaxed);
+            if allocated_at >= self.started_at.load(Ordering::Relaxed) {
+                let class = size_class(layout.size());
+                self.class_frees[class].fetch_add(1, Ordering::Relaxed);
+                self.class_lifetimes[class]
+                    .fetch_add(now - allocated_at, Ordering::Relaxed);
+            }
+        }
+    }
+
+    // Keeps the stamp of the original allocation.
+    unsafe fn realloc(
+        &self,
+        pointer: *mut u8,
+        layout: Layout,
+        new_size: usize,
+    ) -> *mut u8 {
+        let outer = stamped(layout, layout.size()).unwrap();
+        let Some(new_outer) = stamped(layout, new_size) else {
+            return ptr::null_mut();
+        };
+        let allocated_at = read_stamp(pointer, layout.size());
+        let new_pointer = System.realloc(pointer, outer, new_outer.size());
+        if new_pointer.is_null() {
+            return new_pointer;
+        }
+        new_pointer.add(new_size).cast::<u64>().write_unaligned(allocated_at);
+        if self.tick() != 0 {
+            self.reallocations.fetch_add(1, Ordering::Relaxed);
+            self.grow(new_size, layout.size());
+        }
+        new_pointer
+    }
+}
+
+fn size_class(size: usize) -> usize {
+    size.next_power_of_two().trailing_zeros() as usize
+}
+
+// Layout of a block of the given size followed by its stamp.
+fn stamped(layout: Layout, size: usize) -> Option<Layout> {
+    let size = size.checked_add(STAMP_SIZE)?;
+    Layout::from_size_align(size, layout.align()).ok()
+}
+
+unsafe fn read_stamp(pointer: *mut u8, size: usize) -> u64 {
+    pointer.add(size).cast::<u64>().read_unaligned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::CountingAllocator;
+    use std::alloc::{GlobalAlloc, Layout};
+
+    #[test]
+    fn counts() {
+        let allocator = CountingAllocator::new();
+        let layout = Layout::from_size_align(24, 8).unwrap();
+        let other_layout = Layout::from_size_align(8, 8).unwrap();
+
+        allocator.start();
+        let stats = unsafe {
+            let pointer = allocator.alloc(layout);
+            let other_pointer = allocator.alloc(other_layout);
+            let pointer = allocator.realloc(pointer, layout, 100);
+            allocator.dealloc(other_pointer, other_layout);
+            let stats = allocator.stop();
+            let new_layout = Layout::from_size_align(100, 8).unwrap();
+            allocator.dealloc(pointer, new_layout);
+            stats
+        };
+
+        assert_eq!(stats.allocations, 2);
+        assert_eq!(stats.reallocations, 1);
+        assert_eq!(stats.frees, 1);
+        assert_eq!(stats.allocated_bytes, 24 + 8 + 100);
+        assert_eq!(stats.freed_bytes, 24 + 8);
+        assert_eq!(stats.peak_live_bytes, 108);
+        let classes: Vec<_> = stats
+            .size_classes()
+            .map(|class| (class.size, class.count, class.frees))
+            .collect();
+        assert_eq!(classes, vec![(8, 1, 1), (32, 1, 0), (128, 1, 0)]);
+        // Freed after allocating the other block and reallocating.
+        let mean_lifetime = stats.size_classes().next().unwrap().mean_lifetime;
+        assert_eq!(mean_lifetime, 2.0);
+    }
+}
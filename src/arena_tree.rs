@@ -0,0 +1,440 @@
+use crate::{element::Element, residency::PageSet, trace};
+use std::{cmp, collections::VecDeque, error::Error, fmt, mem, str::FromStr};
+
+const NIL: u32 = u32::MAX;
+
+#[derive(Debug, Clone)]
+pub struct TreeLayoutError;
+
+impl fmt::Display for TreeLayoutError {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmtr, "Tree layout must be one of insertion, bfs or dfs")
+    }
+}
+
+impl Error for TreeLayoutError {}
+
+// Order of the nodes in the arena once a tree is created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeLayout {
+    Insertion,
+    BreadthFirst,
+    // Pre-order.
+    DepthFirst,
+}
+
+impl FromStr for TreeLayout {
+    type Err = TreeLayoutError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "insertion" => Ok(TreeLayout::Insertion),
+            "bfs" => Ok(TreeLayout::BreadthFirst),
+            "dfs" => Ok(TreeLayout::DepthFirst),
+            _ => Err(TreeLayoutError),
+        }
+    }
+}
+
+// Same operations as `Tree`, with nodes stored in a vector and linked by
+// indices. Nodes removed by `inc_less_than_with_order` stay in the vector
+// until the next relayout.
+#[derive(Debug, Clone)]
+pub struct ArenaTree<E> {
+    nodes: Vec<Node<E>>,
+    root: u32,
+}
+
+impl<E> Default for ArenaTree<E>
+where
+    E: Element,
+{
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<E> ArenaTree<E>
+where
+    E: Element,
+{
+    pub fn empty() -> Self {
+        Self { nodes: Vec::new(), root: NIL }
+    }
+
+    // The root link when `parent` is NIL.
+    fn child(&self, parent: u32, left: bool) -> u32 {
+        if parent == NIL {
+            self.root
+        } else if left {
+            self.nodes[parent as usize].left
+        } else {
+            self.nodes[parent as usize].right
+        }
+    }
+
+    fn child_mut(&mut self, parent: u32, left: bool) -> &mut u32 {
+        if parent == NIL {
+            &mut self.root
+        } else if left {
+            &mut self.nodes[parent as usize].left
+        } else {
+            &mut self.nodes[parent as usize].right
+        }
+    }
+
+    fn push(&mut self, element: E) -> u32 {
+        assert!(self.nodes.len() < NIL as usize, "arena tree is full");
+        let index = self.nodes.len();
+        self.nodes.push(Node { data: element, left: NIL, right: NIL });
+        trace::touch(&self.nodes[index]);
+        index as u32
+    }
+
+    pub fn insert_with_order(&mut self, element: E) -> bool {
+        let mut parent = NIL;
+        let mut left = false;
+        loop {
+            let current = self.child(parent, left);
+            if current == NIL {
+                let index = self.push(element);
+                *self.child_mut(parent, left) = index;
+                return true;
+            }
+            let node = &self.nodes[current as usize];
+            trace::touch(node);
+            match element.cmp(&node.data) {
+                cmp::Ordering::Equal => return false,
+                cmp::Ordering::Less => left = true,
+                cmp::Ordering::Greater => left = false,
+            }
+            parent = current;
+        }
+    }
+
+    pub fn insert_without_order(&mut self, element: E) {
+        let mut parent = NIL;
+        let mut left = false;
+        let mut reverse = false;
+        loop {
+            let current = self.child(parent, left);
+            if current == NIL {
+                let index = self.push(element);
+                *self.child_mut(parent, left) = index;
+                break;
+            }
+            let node = &self.nodes[current as usize];
+            trace::touch(node);
+            left = match (element.cmp(&node.data), reverse) {
+                (cmp::Ordering::Equal, true)
+                | (cmp::Ordering::Less, false)
+                | (cmp::Ordering::Greater, true) => true,
+                (cmp::Ordering::Equal, false)
+                | (cmp::Ordering::Less, true)
+                | (cmp::Ordering::Greater, false) => false,
+            };
+            reverse = !reverse;
+            parent = current;
+        }
+    }
+
+    pub fn relayout(&mut self, layout: TreeLayout) {
+        let mut order = Vec::with_capacity(self.nodes.len());
+        match layout {
+            TreeLayout::Insertion => return,
+            TreeLayout::BreadthFirst => {
+                let mut queue = VecDeque::from([self.root]);
+                while let Some(index) = queue.pop_front() {
+                    if index != NIL {
+                        order.push(index);
+                        let node = &self.nodes[index as usize];
+                        queue.push_back(node.left);
+                        queue.push_back(node.right);
+                    }
+                }
+            },
+            TreeLayout::DepthFirst => {
+                let mut stack = vec![self.root];
+                while let Some(index) = stack.pop() {
+                    if index != NIL {
+                        order.push(index);
+                        let node = &self.nodes[index as usize];
+                        stack.push(node.right);
+                        stack.push(node.left);
+                    }
+                }
+            },
+        }
+
+        let mut new_indices = vec![NIL; self.nodes.len()];
+        for (new_index, &index) in order.iter().enumerate() {
+            new_indices[index as usize] = new_index as u32;
+        }
+        let remap = |index: u32| {
+            if index == NIL {
+                NIL
+            } else {
+                new_indices[index as usize]
+            }
+        };
+        let nodes = order
+            .iter()
+            .map(|&index| {
+                let node = &self.nodes[index as usize];
+                Node {
+                    data: node.data,
+                    left: remap(node.left),
+                    right: remap(node.right),
+                }
+            })
+            .collect();
+        self.root = remap(self.root);
+        self.nodes = nodes;
+    }
+
+    pub fn find_with_order(&self, element: E) -> bool {
+        let mut current = self.root;
+        while current != NIL {
+            let node = &self.nodes[current as usize];
+            trace::touch(node);
+            current = match element.cmp(&node.data) {
+                cmp::Ordering::Equal => return true,
+                cmp::Ordering::Less => node.left,
+                cmp::Ordering::Greater => node.right,
+            };
+        }
+        false
+    }
+
+    pub fn find_without_order(&self, element: E) -> bool {
+        let mut stack = vec![self.root];
+        while let Some(index) = stack.pop() {
+            if index != NIL {
+                let node = &self.nodes[index as usize];
+                trace::touch(node);
+                if node.data == element {
+                    return true;
+                }
+                stack.push(node.left);
+                stack.push(node.right);
+            }
+        }
+        false
+    }
+
+    pub fn storage(&self, pages: &mut PageSet) {
+        let address = self.nodes.as_ptr() as usize;
+        pages.insert_region(address, self.allocated_bytes());
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        self.nodes.capacity() * mem::size_of::<Node<E>>()
+    }
+
+    pub fn height(&self) -> usize {
+        let mut stack = vec![(self.root, 1)];
+        let mut height = 0;
+        while let Some((index, depth)) = stack.pop() {
+            if index != NIL {
+                height = height.max(depth);
+                let node = &self.nodes[index as usize];
+                stack.push((node.left, depth + 1));
+                stack.push((node.right, depth + 1));
+            }
+        }
+        height
+    }
+
+    fn inc_all(&mut self, start: u32) {
+        let mut stack = vec![start];
+        while let Some(index) = stack.pop() {
+            if index != NIL {
+                let node = &mut self.nodes[index as usize];
+                trace::touch(&*node);
+                node.data = node.data.wrapping_inc();
+                stack.push(node.left);
+                stack.push(node.right);
+            }
+        }
+    }
+
+    // Removes the maximum of the left subtree of `owner` if it equals the
+    // owner's element.
+    fn remove_duplicated_max(&mut self, owner: u32, parent: E) {
+        let mut link_owner = owner;
+        let mut left = true;
+        loop {
+            let current = self.child(link_owner, left);
+            if current == NIL {
+                break;
+            }
+            let node = self.nodes[current as usize];
+            trace::touch(&self.nodes[current as usize]);
+            if node.right == NIL {
+                if node.data == parent {
+                    *self.child_mut(link_owner, left) = node.left;
+                }
+                break;
+            }
+            link_owner = current;
+            left = false;
+        }
+    }
+
+    pub fn inc_less_than_with_order(&mut self, element: E) {
+        let mut current = self.root;
+        if current == NIL {
+            return;
+        }
+        trace::touch(&self.nodes[current as usize]);
+        if self.nodes[current as usize].data < element {
+            self.inc_all(current);
+            return;
+        }
+        loop {
+            let left = self.nodes[current as usize].left;
+            if left == NIL {
+                break;
+            }
+            trace::touch(&self.nodes[left as usize]);
+            if self.nodes[left as usize].data < element {
+                let parent = self.nodes[current as usize].data;
+                self.inc_all(left);
+                self.remove_duplicated_max(current, parent);
+                break;
+            }
+            current = left;
+        }
+    }
+
+    pub fn inc_less_than_without_order(&mut self, element: E) {
+        let mut stack = vec![self.root];
+        while let Some(index) = stack.pop() {
+            if index != NIL {
+                let node = &mut self.nodes[index as usize];
+                trace::touch(&*node);
+                if node.data < element {
+                    node.data = node.data.wrapping_inc();
+                }
+                stack.push(node.left);
+                stack.push(node.right);
+            }
+        }
+    }
+}
+
+impl<'tree, E> IntoIterator for &'tree ArenaTree<E>
+where
+    E: Element,
+{
+    type Item = E;
+    type IntoIter = Iter<'tree, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter { tree: self, stack: Vec::new(), current: self.root }
+    }
+}
+
+#[derive(Debug)]
+pub struct Iter<'tree, E> {
+    tree: &'tree ArenaTree<E>,
+    stack: Vec<u32>,
+    current: u32,
+}
+
+impl<'tree, E> Iterator for Iter<'tree, E>
+where
+    E: Element,
+{
+    type Item = E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.current != NIL {
+            self.stack.push(self.current);
+            self.current = self.tree.nodes[self.current as usize].left;
+        }
+        let index = self.stack.pop()?;
+        let node = &self.tree.nodes[index as usize];
+        self.current = node.right;
+        Some(node.data)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Node<E> {
+    data: E,
+    left: u32,
+    right: u32,
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ArenaTree, TreeLayout};
+    use crate::{element::Element, tree::Tree};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    const ELEMS_IN_PAGE: usize = u64::ELEMS_IN_PAGE;
+
+    #[test]
+    fn find() {
+        let cut_element = (ELEMS_IN_PAGE * 32 + ELEMS_IN_PAGE / 2) as u64;
+
+        let mut tree_with_order = ArenaTree::<u64>::empty();
+        let mut tree_without_order = ArenaTree::<u64>::empty();
+        for i in (cut_element + 1 .. cut_element * 2).step_by(2) {
+            tree_with_order.insert_with_order(i + 1);
+            tree_with_order.insert_with_order(i);
+            tree_without_order.insert_without_order(i + 1);
+            tree_without_order.insert_without_order(i);
+        }
+        for i in (0 .. cut_element).step_by(2) {
+            tree_with_order.insert_with_order(i + 1);
+            tree_with_order.insert_with_order(i);
+            tree_without_order.insert_without_order(i + 1);
+            tree_without_order.insert_without_order(i);
+        }
+        tree_with_order.relayout(TreeLayout::BreadthFirst);
+        tree_without_order.relayout(TreeLayout::DepthFirst);
+
+        assert!(!tree_with_order.find_with_order(cut_element));
+        assert!(!tree_without_order.find_without_order(cut_element));
+
+        tree_with_order.insert_with_order(cut_element);
+        tree_without_order.insert_without_order(cut_element);
+
+        assert!(tree_with_order.find_with_order(cut_element));
+        assert!(tree_without_order.find_without_order(cut_element));
+    }
+
+    #[test]
+    fn same_as_tree() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let elements: Vec<u64> =
+            (0 .. 2000).map(|_| rng.gen_range(0 .. 500)).collect();
+        let targets = [elements[500], elements[1000], elements[1500], 250];
+
+        let layouts = [
+            TreeLayout::Insertion,
+            TreeLayout::BreadthFirst,
+            TreeLayout::DepthFirst,
+        ];
+        for layout in layouts {
+            let mut tree = Tree::empty();
+            let mut arena_tree = ArenaTree::<u64>::empty();
+            for &element in &elements {
+                tree.insert_with_order(element);
+                arena_tree.insert_with_order(element);
+            }
+            arena_tree.relayout(layout);
+            for &target in &targets {
+                tree.inc_less_than_with_order(target);
+                arena_tree.inc_less_than_with_order(target);
+            }
+            arena_tree.relayout(layout);
+
+            let expected: Vec<_> = tree.into_iter().collect();
+            let collected: Vec<_> = arena_tree.into_iter().collect();
+            assert_eq!(collected, expected, "{:?}", layout);
+        }
+    }
+}
@@ -1,37 +1,145 @@
-use std::{cmp, slice};
+use std::{
+    cmp,
+    io,
+    mem,
+    ops::{Deref, DerefMut},
+    slice,
+};
+
+use crate::{
+    config::{ArrayBacking, Config},
+    element::Element,
+    mmap::MappedBuffer,
+    residency::PageSet,
+    trace,
+};
 
-use crate::{Element, ELEMS_IN_PAGE};
+#[derive(Debug, Clone)]
+enum Storage<E>
+where
+    E: Element,
+{
+    Heap(Vec<E>),
+    Mapped(MappedBuffer<E>),
+}
+
+impl<E> Storage<E>
+where
+    E: Element,
+{
+    fn push(&mut self, element: E) -> io::Result<()> {
+        match self {
+            Storage::Heap(vec) => {
+                vec.push(element);
+                Ok(())
+            },
+            Storage::Mapped(buffer) => buffer.push(element),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        match self {
+            Storage::Heap(vec) => vec.capacity(),
+            Storage::Mapped(buffer) => buffer.capacity(),
+        }
+    }
+}
+
+impl<E> Deref for Storage<E>
+where
+    E: Element,
+{
+    type Target = [E];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Storage::Heap(vec) => vec,
+            Storage::Mapped(buffer) => buffer,
+        }
+    }
+}
+
+impl<E> DerefMut for Storage<E>
+where
+    E: Element,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Storage::Heap(vec) => vec,
+            Storage::Mapped(buffer) => buffer,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
-pub struct Array {
-    elements: Vec<Element>,
+pub struct Array<E>
+where
+    E: Element,
+{
+    elements: Storage<E>,
+    elems_in_page: usize,
 }
 
-impl Default for Array {
+impl<E> Default for Array<E>
+where
+    E: Element,
+{
     fn default() -> Self {
         Self::empty()
     }
 }
 
-impl Array {
+impl<E> Array<E>
+where
+    E: Element,
+{
     pub fn empty() -> Self {
-        Self { elements: Vec::new() }
+        Self {
+            elements: Storage::Heap(Vec::new()),
+            elems_in_page: E::ELEMS_IN_PAGE,
+        }
     }
 
-    pub fn append(&mut self, element: Element) {
-        self.elements.push(element);
+    pub fn with_config(config: &Config) -> io::Result<Self> {
+        let elements = match &config.array_backing {
+            ArrayBacking::Heap => Storage::Heap(Vec::new()),
+            ArrayBacking::Mapped(options) => {
+                Storage::Mapped(MappedBuffer::new(options.clone())?)
+            },
+        };
+        let elems_in_page = config.page_size / mem::size_of::<E>();
+        Ok(Self { elements, elems_in_page })
+    }
+
+    // Only fails to grow arrays backed by `mmap`.
+    pub fn append(&mut self, element: E) -> io::Result<()> {
+        self.elements.push(element)?;
+        trace::touch(&self.elements[self.elements.len() - 1]);
+        Ok(())
+    }
+
+    pub fn storage(&self, pages: &mut PageSet) {
+        pages.insert_region(
+            self.elements.as_ptr() as usize,
+            self.elements.capacity() * mem::size_of::<E>(),
+        );
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        self.elements.capacity() * mem::size_of::<E>()
     }
 
     pub fn sort(&mut self) {
         self.elements.sort();
     }
 
-    fn binary_search(&self, element: Element) -> Result<usize, usize> {
+    fn binary_search(&self, element: E) -> Result<usize, usize> {
         let mut left = 0;
         let mut right = self.elements.len();
 
         while left < right {
             let mid = left + (right - left) / 2;
+            trace::touch(&self.elements[mid]);
             match element.cmp(&self.elements[mid]) {
                 cmp::Ordering::Less => right = mid,
                 cmp::Ordering::Equal => return Ok(mid),
@@ -42,10 +150,11 @@ impl Array {
         Err(left)
     }
 
-    pub fn find_good_local(&self, element: Element) -> bool {
+    pub fn find_good_local(&self, element: E) -> bool {
         let mut i = 0;
 
         while i < self.elements.len() {
+            trace::touch(&self.elements[i]);
             if self.elements[i] == element {
                 return true;
             }
@@ -55,9 +164,9 @@ impl Array {
         false
     }
 
-    pub fn find_bad_local(&self, element: Element) -> bool {
+    pub fn find_bad_local(&self, element: E) -> bool {
         let jump_pages = 16;
-        let jump_elements = jump_pages * ELEMS_IN_PAGE;
+        let jump_elements = jump_pages * self.elems_in_page;
 
         let mut offset = 0;
 
@@ -65,6 +174,7 @@ impl Array {
             let mut jump_page = 0;
             let mut index = jump_page * jump_pages + offset;
             while index < self.elements.len() {
+                trace::touch(&self.elements[index]);
                 if self.elements[index] == element {
                     return true;
                 }
@@ -77,13 +187,13 @@ impl Array {
         false
     }
 
-    pub fn find_worse_local(&self, element: Element) -> bool {
-        let rounded = self.elements.len() + ELEMS_IN_PAGE - 1;
-        let pages = rounded / ELEMS_IN_PAGE;
-        let half_size = pages / 2 * ELEMS_IN_PAGE;
+    pub fn find_worse_local(&self, element: E) -> bool {
+        let rounded = self.elements.len() + self.elems_in_page - 1;
+        let pages = rounded / self.elems_in_page;
+        let half_size = pages / 2 * self.elems_in_page;
 
         let jump_pages = 16;
-        let jump_elements = jump_pages * ELEMS_IN_PAGE;
+        let jump_elements = jump_pages * self.elems_in_page;
 
         let mut offset = 0;
 
@@ -95,6 +205,7 @@ impl Array {
                 in_bounds = false;
                 let lower_index = index;
                 if lower_index < half_size {
+                    trace::touch(&self.elements[lower_index]);
                     if self.elements[lower_index] == element {
                         return true;
                     }
@@ -102,6 +213,7 @@ impl Array {
                 }
                 let upper_index = index + half_size;
                 if upper_index < self.elements.len() {
+                    trace::touch(&self.elements[upper_index]);
                     if self.elements[upper_index] == element {
                         return true;
                     }
@@ -116,24 +228,25 @@ impl Array {
         false
     }
 
-    pub fn find_sorted(&self, element: Element) -> bool {
+    pub fn find_sorted(&self, element: E) -> bool {
         self.binary_search(element).is_ok()
     }
 
-    pub fn inc_less_than_good_local(&mut self, element: Element) {
+    pub fn inc_less_than_good_local(&mut self, element: E) {
         let mut i = 0;
 
         while i < self.elements.len() {
+            trace::touch(&self.elements[i]);
             if self.elements[i] < element {
-                self.elements[i] = self.elements[i].wrapping_add(1);
+                self.elements[i] = self.elements[i].wrapping_inc();
             }
             i += 1;
         }
     }
 
-    pub fn inc_less_than_bad_local(&mut self, element: Element) {
+    pub fn inc_less_than_bad_local(&mut self, element: E) {
         let jump_pages = 16;
-        let jump_elements = jump_pages * ELEMS_IN_PAGE;
+        let jump_elements = jump_pages * self.elems_in_page;
 
         let mut offset = 0;
 
@@ -141,8 +254,9 @@ impl Array {
             let mut jump_page = 0;
             let mut index = jump_page * jump_pages + offset;
             while index < self.elements.len() {
+                trace::touch(&self.elements[index]);
                 if self.elements[index] < element {
-                    self.elements[index] = self.elements[index].wrapping_add(1);
+                    self.elements[index] = self.elements[index].wrapping_inc();
                 }
                 jump_page += 1;
                 index = jump_page * jump_pages + offset;
@@ -151,13 +265,13 @@ impl Array {
         }
     }
 
-    pub fn inc_less_than_worse_local(&mut self, element: Element) {
-        let rounded = self.elements.len() + ELEMS_IN_PAGE - 1;
-        let pages = rounded / ELEMS_IN_PAGE;
-        let half_size = pages / 2 * ELEMS_IN_PAGE;
+    pub fn inc_less_than_worse_local(&mut self, element: E) {
+        let rounded = self.elements.len() + self.elems_in_page - 1;
+        let pages = rounded / self.elems_in_page;
+        let half_size = pages / 2 * self.elems_in_page;
 
         let jump_pages = 16;
-        let jump_elements = jump_pages * ELEMS_IN_PAGE;
+        let jump_elements = jump_pages * self.elems_in_page;
 
         let mut offset = 0;
 
@@ -169,17 +283,19 @@ impl Array {
                 in_bounds = false;
                 let lower_index = index;
                 if lower_index < half_size {
+                    trace::touch(&self.elements[lower_index]);
                     if self.elements[lower_index] < element {
                         self.elements[index] =
-                            self.elements[index].wrapping_add(1);
+                            self.elements[index].wrapping_inc();
                     }
                     in_bounds = true;
                 }
                 let upper_index = index + half_size;
                 if upper_index < self.elements.len() {
+                    trace::touch(&self.elements[upper_index]);
                     if self.elements[upper_index] < element {
                         self.elements[index] =
-                            self.elements[index].wrapping_add(1);
+                            self.elements[index].wrapping_inc();
                     }
                     in_bounds = true;
                 }
@@ -190,27 +306,32 @@ impl Array {
         }
     }
 
-    pub fn inc_less_than_sorted(&mut self, element: Element) {
+    pub fn inc_less_than_sorted(&mut self, element: E) {
         let mut upper_bound = match self.binary_search(element) {
             Ok(upper_bound) => upper_bound,
             Err(upper_bound) => upper_bound,
         };
 
         while upper_bound > 0 && self.elements[upper_bound - 1] >= element {
+            trace::touch(&self.elements[upper_bound - 1]);
             upper_bound -= 1;
         }
 
         let mut index = 0;
         while index < upper_bound {
-            self.elements[index] = self.elements[index].wrapping_add(1);
+            trace::touch(&self.elements[index]);
+            self.elements[index] = self.elements[index].wrapping_inc();
             index += 1;
         }
     }
 }
 
-impl<'array> IntoIterator for &'array Array {
-    type Item = Element;
-    type IntoIter = Iter<'array>;
+impl<'array, E> IntoIterator for &'array Array<E>
+where
+    E: Element,
+{
+    type Item = E;
+    type IntoIter = Iter<'array, E>;
 
     fn into_iter(self) -> Self::IntoIter {
         Iter { inner: self.elements.iter() }
@@ -218,12 +339,15 @@ impl<'array> IntoIterator for &'array Array {
 }
 
 #[derive(Debug)]
-pub struct Iter<'array> {
-    inner: slice::Iter<'array, Element>,
+pub struct Iter<'array, E> {
+    inner: slice::Iter<'array, E>,
 }
 
-impl<'array> Iterator for Iter<'array> {
-    type Item = Element;
+impl<'array, E> Iterator for Iter<'array, E>
+where
+    E: Element,
+{
+    type Item = E;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.inner.next().copied()
@@ -233,24 +357,26 @@ impl<'array> Iterator for Iter<'array> {
 #[cfg(test)]
 mod test {
     use super::Array;
-    use crate::{Element, ELEMS_IN_PAGE};
+    use crate::element::Element;
+
+    const ELEMS_IN_PAGE: usize = u64::ELEMS_IN_PAGE;
 
     #[test]
     fn iterate() {
-        let mut array = Array::empty();
-        array.append(10);
-        array.append(3);
-        array.append(5);
-        array.append(9);
+        let mut array = Array::<u64>::empty();
+        array.append(10).unwrap();
+        array.append(3).unwrap();
+        array.append(5).unwrap();
+        array.append(9).unwrap();
         let collected: Vec<_> = array.into_iter().collect();
         assert_eq!(collected, &[10, 3, 5, 9]);
     }
 
     #[test]
     fn find() {
-        let mut array = Array::empty();
+        let mut array = Array::<u64>::empty();
         for i in 0 .. ELEMS_IN_PAGE * 128 + ELEMS_IN_PAGE / 2 {
-            array.append((i % 10) as Element);
+            array.append((i % 10) as u64).unwrap();
         }
 
         let mut sorted_array = array.clone();
@@ -261,9 +387,9 @@ mod test {
         assert!(!array.find_worse_local(11));
         assert!(!sorted_array.find_sorted(11));
 
-        array.append(11);
+        array.append(11).unwrap();
         for i in 0 .. ELEMS_IN_PAGE * 128 + ELEMS_IN_PAGE / 2 {
-            array.append((i % 10) as Element);
+            array.append((i % 10) as u64).unwrap();
         }
 
         let mut sorted_array = array.clone();
@@ -279,9 +405,9 @@ mod test {
     fn inc_less_than() {
         let cut_element = 5;
 
-        let mut array = Array::empty();
+        let mut array = Array::<u64>::empty();
         for i in 0 .. ELEMS_IN_PAGE * 257 + ELEMS_IN_PAGE / 2 {
-            array.append((i % 10) as Element);
+            array.append((i % 10) as u64).unwrap();
         }
 
         let mut good_array = array.clone();
@@ -302,7 +428,7 @@ mod test {
         let mut worse_iter = worse_array.into_iter();
 
         for i in 0 .. ELEMS_IN_PAGE * 257 + ELEMS_IN_PAGE / 2 {
-            let mut expected = (i % 10) as Element;
+            let mut expected = (i % 10) as u64;
             if expected < cut_element {
                 expected += 1;
             }
@@ -0,0 +1,368 @@
+use crate::{
+    element::Element,
+    pool::{NodeBox, Pool},
+    residency::PageSet,
+    trace,
+};
+use std::{alloc::Layout, cmp};
+
+type Link<E> = Option<NodeBox<Node<E>>>;
+
+// AVL tree holding each element once, like `Tree::insert_with_order`.
+#[derive(Debug)]
+pub struct BalancedTree<E> {
+    root: Link<E>,
+}
+
+impl<E> Default for BalancedTree<E>
+where
+    E: Element,
+{
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<E> BalancedTree<E>
+where
+    E: Element,
+{
+    pub fn empty() -> Self {
+        Self { root: None }
+    }
+
+    pub fn node_layout() -> Layout {
+        Layout::new::<Node<E>>()
+    }
+
+    pub fn insert(&mut self, element: E) -> bool {
+        insert_at(&mut self.root, element, &mut NodeBox::new)
+    }
+
+    // The pool must outlive the tree.
+    pub unsafe fn insert_in(&mut self, element: E, pool: &mut Pool) -> bool {
+        insert_at(&mut self.root, element, &mut |node| pool.alloc(node))
+    }
+
+    pub fn remove(&mut self, element: E) -> bool {
+        remove_at(&mut self.root, element)
+    }
+
+    pub fn height(&self) -> usize {
+        height(&self.root) as usize
+    }
+
+    pub fn find(&self, element: E) -> bool {
+        let mut link = &self.root;
+        while let Some(node) = link {
+            trace::touch(&**node);
+            match element.cmp(&node.data) {
+                cmp::Ordering::Equal => return true,
+                cmp::Ordering::Less => link = &node.left,
+                cmp::Ordering::Greater => link = &node.right,
+            }
+        }
+        false
+    }
+
+    pub fn storage(&self, pages: &mut PageSet) {
+        let mut links = vec![&self.root];
+        while let Some(link) = links.pop() {
+            if let Some(node) = link {
+                pages.insert(&**node);
+                links.push(&node.left);
+                links.push(&node.right);
+            }
+        }
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        let mut links = vec![&self.root];
+        let mut bytes = 0;
+        while let Some(link) = links.pop() {
+            if let Some(node) = link {
+                bytes += node.allocated_size();
+                links.push(&node.left);
+                links.push(&node.right);
+            }
+        }
+        bytes
+    }
+
+    // Incrementing keeps the order of the elements, except that the
+    // greatest element less than `element` may become equal to it, in which
+    // case one of the two is removed.
+    pub fn inc_less_than(&mut self, element: E) {
+        let mut collided = false;
+        let mut found = false;
+        let mut link = &mut self.root;
+        while let Some(node) = link {
+            trace::touch(&**node);
+            if node.data < element {
+                inc_all(&mut node.left);
+                node.data = node.data.wrapping_inc();
+                collided |= node.data == element;
+                link = &mut node.right;
+            } else {
+                found |= node.data == element;
+                link = &mut node.left;
+            }
+        }
+        if collided && found {
+            self.remove(element);
+        }
+    }
+}
+
+fn height<E>(link: &Link<E>) -> u8 {
+    link.as_ref().map_or(0, |node| node.height)
+}
+
+fn balance<E>(link: &Link<E>) -> i16 {
+    link.as_ref().map_or(0, |node| {
+        height(&node.left) as i16 - height(&node.right) as i16
+    })
+}
+
+fn update_height<E>(node: &mut Node<E>) {
+    node.height = 1 + height(&node.left).max(height(&node.right));
+}
+
+fn rotate_right<E>(link: &mut Link<E>) {
+    let mut node = link.take().expect("rotated link is not empty");
+    let mut left = node.left.take().expect("rotated node has a left child");
+    node.left = left.right.take();
+    update_height(&mut node);
+    left.right = Some(node);
+    update_height(&mut left);
+    *link = Some(left);
+}
+
+fn rotate_left<E>(link: &mut Link<E>) {
+    let mut node = link.take().expect("rotated link is not empty");
+    let mut right = node.right.take().expect("rotated node has a right child");
+    node.right = right.left.take();
+    update_height(&mut node);
+    right.left = Some(node);
+    update_height(&mut right);
+    *link = Some(right);
+}
+
+fn rebalance<E>(link: &mut Link<E>) {
+    let node = match link {
+        Some(node) => node,
+        None => return,
+    };
+    update_height(node);
+    let node_balance = balance(link);
+    if node_balance > 1 {
+        let node = link.as_mut().expect("link is not empty");
+        if balance(&node.left) < 0 {
+            rotate_left(&mut node.left);
+        }
+        rotate_right(link);
+    } else if node_balance < -1 {
+        let node = link.as_mut().expect("link is not empty");
+        if balance(&node.right) > 0 {
+            rotate_right(&mut node.right);
+        }
+        rotate_left(link);
+    }
+}
+
+fn insert_at<E, F>(link: &mut Link<E>, element: E, alloc: &mut F) -> bool
+where
+    E: Element,
+    F: FnMut(Node<E>) -> NodeBox<Node<E>>,
+{
+    let node = match link {
+        Some(node) => node,
+        None => {
+            let node = link.insert(alloc(Node {
+                data: element,
+                height: 1,
+                left: None,
+                right: None,
+            }));
+            trace::touch(&**node);
+            return true;
+        },
+    };
+    trace::touch(&**node);
+    let inserted = match element.cmp(&node.data) {
+        cmp::Ordering::Equal => false,
+        cmp::Ordering::Less => insert_at(&mut node.left, element, alloc),
+        cmp::Ordering::Greater => insert_at(&mut node.right, element, alloc),
+    };
+    if inserted {
+        rebalance(link);
+    }
+    inserted
+}
+
+fn remove_at<E>(link: &mut Link<E>, element: E) -> bool
+where
+    E: Element,
+{
+    let node = match link {
+        Some(node) => node,
+        None => return false,
+    };
+    trace::touch(&**node);
+    let removed = match element.cmp(&node.data) {
+        cmp::Ordering::Less => remove_at(&mut node.left, element),
+        cmp::Ordering::Greater => remove_at(&mut node.right, element),
+        cmp::Ordering::Equal => {
+            if node.left.is_none() {
+                *link = node.right.take();
+            } else if node.right.is_none() {
+                *link = node.left.take();
+            } else {
+                node.data = remove_min(&mut node.right);
+            }
+            true
+        },
+    };
+    if removed {
+        rebalance(link);
+    }
+    removed
+}
+
+fn remove_min<E>(link: &mut Link<E>) -> E
+where
+    E: Element,
+{
+    let node = link.as_mut().expect("subtree is not empty");
+    trace::touch(&**node);
+    if node.left.is_none() {
+        let data = node.data;
+        *link = node.right.take();
+        return data;
+    }
+    let data = remove_min(&mut node.left);
+    rebalance(link);
+    data
+}
+
+fn inc_all<E>(link: &mut Link<E>)
+where
+    E: Element,
+{
+    let mut links = vec![link];
+    while let Some(link) = links.pop() {
+        if let Some(node) = link.as_deref_mut() {
+            trace::touch(&*node);
+            node.data = node.data.wrapping_inc();
+            links.push(&mut node.left);
+            links.push(&mut node.right);
+        }
+    }
+}
+
+fn clone_link<E>(link: &Link<E>) -> Link<E>
+where
+    E: Element,
+{
+    link.as_ref().map(|node| {
+        NodeBox::new(Node {
+            data: node.data,
+            height: node.height,
+            left: clone_link(&node.left),
+            right: clone_link(&node.right),
+        })
+    })
+}
+
+impl<E> Clone for BalancedTree<E>
+where
+    E: Element,
+{
+    fn clone(&self) -> Self {
+        Self { root: clone_link(&self.root) }
+    }
+}
+
+impl<'tree, E> IntoIterator for &'tree BalancedTree<E>
+where
+    E: Element,
+{
+    type Item = E;
+    type IntoIter = Iter<'tree, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter { stack: Vec::new(), current: &self.root }
+    }
+}
+
+#[derive(Debug)]
+pub struct Iter<'tree, E> {
+    stack: Vec<&'tree Node<E>>,
+    current: &'tree Link<E>,
+}
+
+impl<'tree, E> Iterator for Iter<'tree, E>
+where
+    E: Element,
+{
+    type Item = E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.current {
+            self.stack.push(node);
+            self.current = &node.left;
+        }
+        let node = self.stack.pop()?;
+        self.current = &node.right;
+        Some(node.data)
+    }
+}
+
+#[derive(Debug)]
+struct Node<E> {
+    data: E,
+    height: u8,
+    left: Link<E>,
+    right: Link<E>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::BalancedTree;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn sorted_input() {
+        let mut tree = BalancedTree::<u64>::empty();
+        for i in 0 .. 1 << 12 {
+            assert!(tree.insert(i));
+        }
+        assert!(!tree.insert(100));
+        assert!(tree.height() <= 13);
+        assert!(tree.find(0) && tree.find(4095) && !tree.find(4096));
+        assert!(tree.into_iter().eq(0 .. 1 << 12));
+    }
+
+    #[test]
+    fn same_as_set() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut tree = BalancedTree::<u64>::empty();
+        let mut set = BTreeSet::new();
+        for _ in 0 .. 2000 {
+            let element = rng.gen_range(0 .. 500);
+            tree.insert(element);
+            set.insert(element);
+        }
+        for _ in 0 .. 50 {
+            let target = rng.gen_range(0 .. 600);
+            tree.inc_less_than(target);
+            set = set
+                .into_iter()
+                .map(|element| element + (element < target) as u64)
+                .collect();
+            assert!(tree.into_iter().eq(set.iter().copied()));
+        }
+        assert!(tree.height() <= 10);
+    }
+}
@@ -0,0 +1,531 @@
+use crate::{
+    element::Element,
+    pool::{self, BlockPtr, Pool},
+    residency::PageSet,
+    trace,
+};
+use std::{alloc::Layout, marker::PhantomData, mem, ptr, slice};
+
+// Fewer keys would leave nothing to split.
+const MIN_CAPACITY: usize = 3;
+
+// B+-tree keeping duplicates, like a sorted array. Every separator equals
+// the least element of the subtree to its right, which is where elements
+// equal to it are inserted.
+#[derive(Debug)]
+pub struct BTree<E>
+where
+    E: Element,
+{
+    root: NodePtr<E>,
+    shape: Shape<E>,
+}
+
+impl<E> BTree<E>
+where
+    E: Element,
+{
+    // Nodes take `node_size` bytes, or the least that holds a few keys, and
+    // split once they are full.
+    pub fn new(node_size: usize) -> Self {
+        let shape = Shape::new(node_size);
+        let root = shape.init(BlockPtr::alloc(shape.layout), true);
+        Self { root, shape }
+    }
+
+    pub fn node_layout(&self) -> Layout {
+        self.shape.layout
+    }
+
+    pub fn leaf_capacity(&self) -> usize {
+        self.shape.leaf_capacity
+    }
+
+    pub fn insert(&mut self, element: E) {
+        self.insert_with(element, &mut BlockPtr::alloc);
+    }
+
+    // The pool must outlive the tree.
+    pub unsafe fn insert_in(&mut self, element: E, pool: &mut Pool) {
+        self.insert_with(element, &mut |layout| pool.alloc_block(layout));
+    }
+
+    fn insert_with<F>(&mut self, element: E, alloc: &mut F)
+    where
+        F: FnMut(Layout) -> BlockPtr,
+    {
+        let split = self.insert_into(self.root, element, alloc);
+        if let Some((separator, right)) = split {
+            let root = self.shape.init(alloc(self.shape.layout), false);
+            unsafe {
+                self.shape.keys(root)[0] = separator;
+                let children = self.shape.children(root);
+                children[0] = self.root;
+                children[1] = right;
+            }
+            root.set_len(1);
+            self.root = root;
+        }
+    }
+
+    // Returns the separator and right half of the node if it split.
+    fn insert_into<F>(
+        &mut self,
+        node: NodePtr<E>,
+        element: E,
+        alloc: &mut F,
+    ) -> Option<(E, NodePtr<E>)>
+    where
+        F: FnMut(Layout) -> BlockPtr,
+    {
+        let shape = &self.shape;
+        let len = node.len();
+        let keys = unsafe { shape.keys(node) };
+        let index = count_less(&keys[.. len], element, true);
+        if node.is_leaf() {
+            keys.copy_within(index .. len, index + 1);
+            keys[index] = element;
+            trace::touch(&keys[index]);
+            node.set_len(len + 1);
+        } else {
+            let child = unsafe { shape.children(node)[index] };
+            let split = self.insert_into(child, element, alloc);
+            if let Some((separator, right)) = split {
+                keys.copy_within(index .. len, index + 1);
+                keys[index] = separator;
+                let children = unsafe { self.shape.children(node) };
+                children.copy_within(index + 1 .. len + 1, index + 2);
+                children[index + 1] = right;
+                node.set_len(len + 1);
+            }
+        }
+
+        let len = node.len();
+        if len < self.shape.capacity(node) {
+            return None;
+        }
+        let middle = len / 2;
+        let right = self.shape.init(alloc(self.shape.layout), node.is_leaf());
+        let right_keys = unsafe { self.shape.keys(right) };
+        if node.is_leaf() {
+            right_keys[.. len - middle].copy_from_slice(&keys[middle .. len]);
+            right.set_len(len - middle);
+            node.set_len(middle);
+            Some((right_keys[0], right))
+        } else {
+            right_keys[.. len - middle - 1]
+                .copy_from_slice(&keys[middle + 1 .. len]);
+            unsafe {
+                let children = self.shape.children(node);
+                self.shape.children(right)[.. len - middle]
+                    .copy_from_slice(&children[middle + 1 .. len + 1]);
+            }
+            right.set_len(len - middle - 1);
+            node.set_len(middle);
+            Some((keys[middle], right))
+        }
+    }
+
+    pub fn find(&self, element: E) -> bool {
+        let mut node = self.root;
+        loop {
+            let keys = unsafe { &self.shape.keys(node)[.. node.len()] };
+            let index = count_less(keys, element, false);
+            if index < keys.len() && keys[index] == element {
+                return true;
+            }
+            if node.is_leaf() {
+                return false;
+            }
+            node = unsafe { self.shape.children(node)[index] };
+        }
+    }
+
+    // Elements less than `element` are a prefix of every node.
+    pub fn inc_less_than(&mut self, element: E) {
+        let mut node = self.root;
+        loop {
+            let keys = unsafe { &mut self.shape.keys(node)[.. node.len()] };
+            let index = count_less(keys, element, false);
+            for key in &mut keys[.. index] {
+                trace::touch(&*key);
+                *key = key.wrapping_inc();
+            }
+            if node.is_leaf() {
+                break;
+            }
+            let children = unsafe { self.shape.children(node) };
+            for &child in &children[.. index] {
+                self.inc_all(child);
+            }
+            node = children[index];
+        }
+    }
+
+    fn inc_all(&mut self, node: NodePtr<E>) {
+        let mut nodes = vec![node];
+        while let Some(node) = nodes.pop() {
+            for key in unsafe { &mut self.shape.keys(node)[.. node.len()] } {
+                trace::touch(&*key);
+                *key = key.wrapping_inc();
+            }
+            nodes.extend(self.children(node));
+        }
+    }
+
+    // Children of inner nodes, none for leaves.
+    fn children(&self, node: NodePtr<E>) -> &[NodePtr<E>] {
+        if node.is_leaf() {
+            &[]
+        } else {
+            unsafe { &self.shape.children(node)[.. node.len() + 1] }
+        }
+    }
+
+    pub fn storage(&self, pages: &mut PageSet) {
+        let mut nodes = vec![self.root];
+        while let Some(node) = nodes.pop() {
+            pages.insert_region(
+                node.block.as_ptr() as usize,
+                self.shape.layout.size(),
+            );
+            nodes.extend(self.children(node));
+        }
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        let mut nodes = vec![self.root];
+        let mut bytes = mem::size_of::<Self>();
+        while let Some(node) = nodes.pop() {
+            bytes += node.block.allocated_size();
+            nodes.extend(self.children(node));
+        }
+        bytes
+    }
+
+    pub fn height(&self) -> usize {
+        let mut node = self.root;
+        let mut height = 1;
+        while let Some(&child) = self.children(node).first() {
+            node = child;
+            height += 1;
+        }
+        height
+    }
+}
+
+impl<E> Clone for BTree<E>
+where
+    E: Element,
+{
+    fn clone(&self) -> Self {
+        let copy = |node: NodePtr<E>| {
+            let block = BlockPtr::alloc(self.shape.layout);
+            let new_node = self.shape.init(block, node.is_leaf());
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    node.block.as_ptr(),
+                    new_node.block.as_ptr(),
+                    self.shape.layout.size(),
+                );
+            }
+            new_node
+        };
+        let root = copy(self.root);
+        let mut nodes = vec![root];
+        while let Some(node) = nodes.pop() {
+            if node.is_leaf() {
+                continue;
+            }
+            let children = unsafe { self.shape.children(node) };
+            for child in &mut children[.. node.len() + 1] {
+                *child = copy(*child);
+                nodes.push(*child);
+            }
+        }
+        Self { root, shape: self.shape }
+    }
+}
+
+impl<E> Drop for BTree<E>
+where
+    E: Element,
+{
+    fn drop(&mut self) {
+        let mut nodes = vec![self.root];
+        while let Some(node) = nodes.pop() {
+            nodes.extend(self.children(node));
+            unsafe { node.block.free(self.shape.layout) }
+        }
+    }
+}
+
+// Binary search touching every key it reads.
+fn count_less<E>(keys: &[E], element: E, or_equal: bool) -> usize
+where
+    E: Element,
+{
+    let mut left = 0;
+    let mut right = keys.len();
+    while left < right {
+        let mid = left + (right - left) / 2;
+        trace::touch(&keys[mid]);
+        if keys[mid] < element || (or_equal && keys[mid] == element) {
+            left = mid + 1;
+        } else {
+            right = mid;
+        }
+    }
+    left
+}
+
+#[derive(Debug)]
+#[repr(C)]
+struct Header {
+    len: u32,
+    is_leaf: bool,
+}
+
+// A node is a single block starting with its header, followed by keys and,
+// in inner nodes, one more child pointer than keys.
+#[derive(Debug)]
+struct NodePtr<E> {
+    block: BlockPtr,
+    _marker: PhantomData<E>,
+}
+
+impl<E> Clone for NodePtr<E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E> Copy for NodePtr<E> {}
+
+impl<E> NodePtr<E> {
+    fn header(self) -> *mut Header {
+        self.block.as_ptr() as *mut Header
+    }
+
+    fn len(self) -> usize {
+        unsafe { (*self.header()).len as usize }
+    }
+
+    fn set_len(self, len: usize) {
+        unsafe { (*self.header()).len = len as u32 }
+    }
+
+    fn is_leaf(self) -> bool {
+        unsafe { (*self.header()).is_leaf }
+    }
+}
+
+// Where keys and children lie in the nodes of a tree, which all have the
+// same layout.
+#[derive(Debug)]
+struct Shape<E> {
+    layout: Layout,
+    keys_offset: usize,
+    children_offset: usize,
+    leaf_capacity: usize,
+    inner_capacity: usize,
+    _marker: PhantomData<E>,
+}
+
+impl<E> Clone for Shape<E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E> Copy for Shape<E> {}
+
+impl<E> Shape<E>
+where
+    E: Element,
+{
+    fn new(node_size: usize) -> Self {
+        let pointer_size = mem::size_of::<NodePtr<E>>();
+        let keys_offset =
+            mem::size_of::<Header>().next_multiple_of(mem::align_of::<E>());
+        let children_offset = |capacity: usize| {
+            (keys_offset + capacity * mem::size_of::<E>())
+                .next_multiple_of(mem::align_of::<NodePtr<E>>())
+        };
+        let inner_size = |capacity: usize| {
+            children_offset(capacity) + (capacity + 1) * pointer_size
+        };
+
+        let mut inner_capacity = MIN_CAPACITY;
+        while inner_size(inner_capacity + 1) <= node_size {
+            inner_capacity += 1;
+        }
+        let size = inner_size(inner_capacity).max(node_size);
+        let min_align =
+            mem::align_of::<E>().max(mem::align_of::<NodePtr<E>>());
+        Self {
+            layout: pool::block_layout(size, min_align),
+            keys_offset,
+            children_offset: children_offset(inner_capacity),
+            leaf_capacity: (size - keys_offset) / mem::size_of::<E>(),
+            inner_capacity,
+            _marker: PhantomData,
+        }
+    }
+
+    fn capacity(&self, node: NodePtr<E>) -> usize {
+        if node.is_leaf() {
+            self.leaf_capacity
+        } else {
+            self.inner_capacity
+        }
+    }
+
+    // Free slots hold `E::MAX` and dangling children, so that whole slices
+    // of them can be taken.
+    fn init(&self, block: BlockPtr, is_leaf: bool) -> NodePtr<E> {
+        let node = NodePtr { block, _marker: PhantomData };
+        unsafe {
+            node.header().write(Header { len: 0, is_leaf });
+            let keys = block.as_ptr().add(self.keys_offset) as *mut E;
+            for index in 0 .. self.capacity(node) {
+                keys.add(index).write(E::MAX);
+            }
+            if !is_leaf {
+                let children = block.as_ptr().add(self.children_offset)
+                    as *mut NodePtr<E>;
+                for index in 0 .. self.inner_capacity + 1 {
+                    children.add(index).write(NodePtr {
+                        block: BlockPtr::dangling(),
+                        _marker: PhantomData,
+                    });
+                }
+            }
+        }
+        node
+    }
+
+    // Every key slot of the node, to be used by one caller at a time.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn keys<'node>(&self, node: NodePtr<E>) -> &'node mut [E] {
+        let keys = node.block.as_ptr().add(self.keys_offset) as *mut E;
+        slice::from_raw_parts_mut(keys, self.capacity(node))
+    }
+
+    // Every child slot of an inner node, to be used by one caller at a time.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn children<'node>(
+        &self,
+        node: NodePtr<E>,
+    ) -> &'node mut [NodePtr<E>] {
+        debug_assert!(!node.is_leaf());
+        let children = node.block.as_ptr().add(self.children_offset)
+            as *mut NodePtr<E>;
+        slice::from_raw_parts_mut(children, self.inner_capacity + 1)
+    }
+}
+
+impl<'tree, E> IntoIterator for &'tree BTree<E>
+where
+    E: Element,
+{
+    type Item = E;
+    type IntoIter = Iter<'tree, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter { tree: self, stack: vec![(self.root, 0)] }
+    }
+}
+
+// Each entry is a node and the index of its next key, or next child for
+// inner nodes.
+#[derive(Debug)]
+pub struct Iter<'tree, E>
+where
+    E: Element,
+{
+    tree: &'tree BTree<E>,
+    stack: Vec<(NodePtr<E>, usize)>,
+}
+
+impl<'tree, E> Iterator for Iter<'tree, E>
+where
+    E: Element,
+{
+    type Item = E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node, index) = self.stack.pop()?;
+            if node.is_leaf() {
+                if index < node.len() {
+                    self.stack.push((node, index + 1));
+                    return Some(unsafe { self.tree.shape.keys(node)[index] });
+                }
+            } else if index <= node.len() {
+                // Separators are copies of elements in leaves.
+                self.stack.push((node, index + 1));
+                let child = self.tree.children(node)[index];
+                self.stack.push((child, 0));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BTree;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn aligned_nodes() {
+        let mut tree = BTree::<u64>::new(256);
+        for element in 0 .. 1000 {
+            tree.insert(element);
+        }
+        assert_eq!(tree.shape.layout.size(), 256);
+        assert_eq!(tree.shape.layout.align(), 256);
+        let mut nodes = vec![tree.root];
+        while let Some(node) = nodes.pop() {
+            assert_eq!(node.block.as_ptr() as usize % 256, 0);
+            nodes.extend(tree.children(node));
+        }
+        assert!(tree.height() > 1);
+        assert!(tree.clone().into_iter().eq(tree.into_iter()));
+    }
+
+    #[test]
+    fn same_as_sorted() {
+        let mut rng = StdRng::seed_from_u64(5);
+        for node_size in [64, 256, 4096] {
+            let mut tree = BTree::<u64>::new(node_size);
+            let mut sorted = Vec::new();
+            for _ in 0 .. 5000 {
+                let element = rng.gen_range(0 .. 1000);
+                tree.insert(element);
+                sorted.push(element);
+            }
+            sorted.sort();
+            assert!(tree.into_iter().eq(sorted.iter().copied()));
+
+            for _ in 0 .. 20 {
+                let target = rng.gen_range(0 .. 1100);
+                tree.inc_less_than(target);
+                for element in &mut sorted {
+                    *element += (*element < target) as u64;
+                }
+                assert!(tree.into_iter().eq(sorted.iter().copied()));
+                for _ in 0 .. 20 {
+                    let element = rng.gen_range(0 .. 1100);
+                    assert_eq!(
+                        tree.find(element),
+                        sorted.binary_search(&element).is_ok(),
+                        "{} in nodes of {} B",
+                        element,
+                        node_size,
+                    );
+                }
+            }
+        }
+    }
+}
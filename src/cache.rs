@@ -0,0 +1,133 @@
+use crate::{residency::PageSet, reuse_distance::CACHE_LINE_SIZE};
+use std::{error::Error, fmt, fs, hint, io, ptr, str::FromStr};
+
+const CACHE_DIR: &str = "/sys/devices/system/cpu/cpu0/cache";
+const DEFAULT_LLC_SIZE: usize = 32 << 20;
+
+#[derive(Debug, Clone)]
+pub struct CacheStateError;
+
+impl fmt::Display for CacheStateError {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmtr, "Cache state must be either cold or warm")
+    }
+}
+
+impl Error for CacheStateError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheState {
+    Cold,
+    Warm,
+}
+
+impl CacheState {
+    pub fn name(self) -> &'static str {
+        match self {
+            CacheState::Cold => "cold",
+            CacheState::Warm => "warm",
+        }
+    }
+}
+
+impl FromStr for CacheState {
+    type Err = CacheStateError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "cold" => Ok(CacheState::Cold),
+            "warm" => Ok(CacheState::Warm),
+            _ => Err(CacheStateError),
+        }
+    }
+}
+
+// Parses sizes such as `32K` or `8M` found in sysfs cache descriptions.
+fn parse_size(size: &str) -> Option<usize> {
+    let size = size.trim();
+    let (digits, unit) = match size.strip_suffix('K') {
+        Some(digits) => (digits, 1 << 10),
+        None => match size.strip_suffix('M') {
+            Some(digits) => (digits, 1 << 20),
+            None => (size, 1),
+        },
+    };
+    digits.parse::<usize>().ok().map(|digits| digits * unit)
+}
+
+pub fn last_level_cache_size() -> Option<usize> {
+    fs::read_dir(CACHE_DIR)
+        .ok()?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let level = fs::read_to_string(path.join("level")).ok()?;
+            let level: u32 = level.trim().parse().ok()?;
+            let size = fs::read_to_string(path.join("size")).ok()?;
+            Some((level, parse_size(&size)?))
+        })
+        .max()
+        .map(|(_, size)| size)
+}
+
+// Evicts caches by streaming through a buffer twice as large as the last
+// level cache.
+#[derive(Debug)]
+pub struct Evictor {
+    buffer: Vec<u8>,
+}
+
+impl Evictor {
+    pub fn new() -> Self {
+        let size = last_level_cache_size().unwrap_or(DEFAULT_LLC_SIZE) * 2;
+        Self { buffer: vec![1; size] }
+    }
+
+    pub fn evict(&mut self) {
+        for i in (0 .. self.buffer.len()).step_by(CACHE_LINE_SIZE) {
+            self.buffer[i] = self.buffer[i].wrapping_add(1);
+        }
+        hint::black_box(&mut self.buffer);
+    }
+}
+
+impl Default for Evictor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Reads one byte of every cache line of the pages.
+pub fn warm(pages: &mut PageSet) {
+    for (address, length) in pages.regions() {
+        for offset in (0 .. length).step_by(CACHE_LINE_SIZE) {
+            unsafe {
+                ptr::read_volatile((address + offset) as *const u8);
+            }
+        }
+    }
+}
+
+// Only pages that can be reclaimed, such as anonymous pages with swap
+// available, are actually paged out.
+pub fn page_out(pages: &mut PageSet) -> io::Result<()> {
+    for (address, length) in pages.regions() {
+        let address = address as *mut libc::c_void;
+        if unsafe { libc::madvise(address, length, libc::MADV_PAGEOUT) } < 0 {
+            Err(io::Error::last_os_error())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_size;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("32K\n"), Some(32 * 1024));
+        assert_eq!(parse_size("8M"), Some(8 * 1024 * 1024));
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("big"), None);
+    }
+}
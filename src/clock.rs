@@ -0,0 +1,211 @@
+use std::{error::Error, fmt, io, mem, str::FromStr, thread, time::Duration};
+
+const OVERHEAD_SAMPLES: usize = 1001;
+const CALIBRATION_TIME: Duration = Duration::from_millis(20);
+
+#[derive(Debug, Clone)]
+pub struct ClockSourceError;
+
+impl fmt::Display for ClockSourceError {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmtr,
+            "Clock must be one of monotonic, monotonic-raw, thread-cputime, \
+             process-cputime, rdtsc or rdtscp"
+        )
+    }
+}
+
+impl Error for ClockSourceError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockSource {
+    Monotonic,
+    MonotonicRaw,
+    ThreadCpuTime,
+    ProcessCpuTime,
+    Tsc,
+    Tscp,
+}
+
+impl ClockSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            ClockSource::Monotonic => "monotonic",
+            ClockSource::MonotonicRaw => "monotonic-raw",
+            ClockSource::ThreadCpuTime => "thread-cputime",
+            ClockSource::ProcessCpuTime => "process-cputime",
+            ClockSource::Tsc => "rdtsc",
+            ClockSource::Tscp => "rdtscp",
+        }
+    }
+
+    pub fn is_tsc(self) -> bool {
+        self.clock_id().is_none()
+    }
+
+    fn clock_id(self) -> Option<libc::clockid_t> {
+        match self {
+            ClockSource::Monotonic => Some(libc::CLOCK_MONOTONIC),
+            ClockSource::MonotonicRaw => Some(libc::CLOCK_MONOTONIC_RAW),
+            ClockSource::ThreadCpuTime => Some(libc::CLOCK_THREAD_CPUTIME_ID),
+            ClockSource::ProcessCpuTime => {
+                Some(libc::CLOCK_PROCESS_CPUTIME_ID)
+            },
+            ClockSource::Tsc | ClockSource::Tscp => None,
+        }
+    }
+}
+
+impl FromStr for ClockSource {
+    type Err = ClockSourceError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "monotonic" => Ok(ClockSource::Monotonic),
+            "monotonic-raw" => Ok(ClockSource::MonotonicRaw),
+            "thread-cputime" => Ok(ClockSource::ThreadCpuTime),
+            "process-cputime" => Ok(ClockSource::ProcessCpuTime),
+            "rdtsc" => Ok(ClockSource::Tsc),
+            "rdtscp" => Ok(ClockSource::Tscp),
+            _ => Err(ClockSourceError),
+        }
+    }
+}
+
+fn clock_gettime(clock_id: libc::clockid_t) -> u64 {
+    let mut time: libc::timespec = unsafe { mem::zeroed() };
+    unsafe {
+        libc::clock_gettime(clock_id, &mut time);
+    }
+    time.tv_sec as u64 * 1_000_000_000 + time.tv_nsec as u64
+}
+
+// Fences keep the timed instructions from being reordered around the read.
+#[cfg(target_arch = "x86_64")]
+fn read_tsc(serializing: bool) -> u64 {
+    use std::arch::x86_64::{__rdtscp, _mm_lfence, _rdtsc};
+
+    unsafe {
+        if serializing {
+            let mut aux = 0;
+            let ticks = __rdtscp(&mut aux);
+            _mm_lfence();
+            ticks
+        } else {
+            _mm_lfence();
+            let ticks = _rdtsc();
+            _mm_lfence();
+            ticks
+        }
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn read_tsc(_serializing: bool) -> u64 {
+    unreachable!("the time stamp counter is only read on x86_64")
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Clock {
+    source: ClockSource,
+    // Nanoseconds per tick, 1 for everything but the time stamp counter.
+    tick_nanos: f64,
+    overhead: u64,
+}
+
+impl Clock {
+    pub fn new(source: ClockSource) -> io::Result<Self> {
+        let mut this = Self { source, tick_nanos: 1.0, overhead: 0 };
+        if source.is_tsc() {
+            if !cfg!(target_arch = "x86_64") {
+                Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "the time stamp counter is only read on x86_64",
+                ))?;
+            }
+            this.calibrate();
+        }
+        this.overhead = this.measure_overhead();
+        Ok(this)
+    }
+
+    fn calibrate(&mut self) {
+        let nanos_before = clock_gettime(libc::CLOCK_MONOTONIC_RAW);
+        let ticks_before = self.now();
+        thread::sleep(CALIBRATION_TIME);
+        let nanos_after = clock_gettime(libc::CLOCK_MONOTONIC_RAW);
+        let ticks_after = self.now();
+        self.tick_nanos = (nanos_after - nanos_before) as f64
+            / (ticks_after - ticks_before).max(1) as f64;
+    }
+
+    // Median time between two back to back reads.
+    fn measure_overhead(&self) -> u64 {
+        let mut samples: Vec<u64> = (0 .. OVERHEAD_SAMPLES)
+            .map(|_| {
+                let start = self.now();
+                self.elapsed(start)
+            })
+            .collect();
+        samples.sort_unstable();
+        samples[samples.len() / 2]
+    }
+
+    pub fn source(&self) -> ClockSource {
+        self.source
+    }
+
+    pub fn overhead(&self) -> u64 {
+        self.overhead
+    }
+
+    pub fn tick_nanos(&self) -> f64 {
+        self.tick_nanos
+    }
+
+    pub fn now(&self) -> u64 {
+        match self.source.clock_id() {
+            Some(clock_id) => clock_gettime(clock_id),
+            None => read_tsc(self.source == ClockSource::Tscp),
+        }
+    }
+
+    // Nanoseconds since `start`, less the overhead of reading the clock.
+    pub fn elapsed(&self, start: u64) -> u64 {
+        let ticks = self.now().saturating_sub(start);
+        let nanos = (ticks as f64 * self.tick_nanos) as u64;
+        nanos.saturating_sub(self.overhead)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Clock, ClockSource};
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn elapsed() {
+        let mut sources =
+            vec![ClockSource::Monotonic, ClockSource::MonotonicRaw];
+        if cfg!(target_arch = "x86_64") {
+            sources.push(ClockSource::Tscp);
+        }
+        for source in sources {
+            let clock = Clock::new(source).unwrap();
+            let start = clock.now();
+            thread::sleep(Duration::from_millis(5));
+            let elapsed = clock.elapsed(start);
+            assert!(elapsed >= 4_000_000, "{:?}: {}", source, elapsed);
+            assert!(elapsed < 1_000_000_000, "{:?}: {}", source, elapsed);
+        }
+    }
+
+    #[test]
+    fn cpu_time() {
+        let clock = Clock::new(ClockSource::ThreadCpuTime).unwrap();
+        let start = clock.now();
+        thread::sleep(Duration::from_millis(20));
+        assert!(clock.elapsed(start) < 20_000_000);
+    }
+}
@@ -1,280 +1,1277 @@
 use crate::{
+    arena_tree::ArenaTree as ArenaTreeImpl,
     array::Array as ArrayImpl,
+    balanced_tree::BalancedTree as BalancedTreeImpl,
+    btree::BTree as BTreeImpl,
+    config::Config,
+    doubly_linked_list::DoublyLinkedList as DoublyLinkedListImpl,
+    element::Element,
+    eytzinger::EytzingerArray as EytzingerArrayImpl,
+    fenwick::FenwickArray as FenwickArrayImpl,
+    hash_table::{ChainedTable as ChainedTableImpl, OpenTable as OpenTableImpl},
     linked_list::LinkedList as LinkedListImpl,
+    pool::{Placement, Pool},
+    record::Recorder,
+    residency::{self, PageSet},
+    self_organizing::{Heuristic, SelfOrganizingList},
+    skip_list::SkipList as SkipListImpl,
     tree::Tree as TreeImpl,
-    Element,
+    unrolled_list::UnrolledList as UnrolledListImpl,
+    veb::VebArray as VebArrayImpl,
+    xor_linked_list::XorLinkedList as XorLinkedListImpl,
 };
-use std::{io, mem, time::Instant};
+use std::{alloc::Layout, io, mem, rc::Rc};
 
-#[derive(Debug, Clone, Copy, serde::Serialize)]
-struct RecordRow<'mode, 'oper> {
-    mode: &'mode str,
-    size: usize,
-    operation: &'oper str,
-    collection: &'static str,
-    nanoseconds: u128,
-}
-
-pub trait Collection: Sized {
+pub trait Collection<E>: Sized + Clone
+where
+    E: Element,
+{
     const NAME: &'static str;
 
-    fn create(elements: &[Element]) -> Self;
+    fn create(elements: &[E], config: &Config) -> io::Result<Self>;
+
+    // Self-organizing lists reorder their nodes when they find an element.
+    fn find(&mut self, element: E) -> bool;
+
+    fn inc_less_than(&mut self, element: E);
+
+    fn storage(&self, pages: &mut PageSet);
+
+    // Bytes allocated for the elements and everything around them.
+    fn allocated_bytes(&self) -> usize;
 
-    fn find(&self, element: Element) -> bool;
+    // Trees merging duplicates store fewer elements than their input.
+    fn element_count(&self) -> usize;
 
-    fn inc_less_than(&mut self, element: Element);
+    // Only trees have a height.
+    fn height(&self) -> Option<usize> {
+        None
+    }
 
     fn record_create<W>(
-        elements: &[Element],
-        mode_name: &str,
+        elements: &[E],
+        config: &Config,
         oper_name: &str,
-        csv_writer: &mut csv::Writer<W>,
+        recorder: &mut Recorder<W>,
     ) -> io::Result<Self>
     where
         W: io::Write,
     {
-        let then = Instant::now();
-        let this = Self::create(elements);
-        let elapsed = then.elapsed();
-        let row = RecordRow {
-            mode: mode_name,
-            size: elements.len() * mem::size_of::<Element>(),
-            operation: oper_name,
-            collection: Self::NAME,
-            nanoseconds: elapsed.as_nanos(),
-        };
-        csv_writer.serialize(row)?;
+        let size = mem::size_of_val(elements);
+        // The input stands for the collection before it is created.
+        recorder.prepare_cache(|pages| {
+            pages.insert_region(elements.as_ptr() as usize, size)
+        })?;
+        let rss_before = residency::resident_set_size()?;
+        let this = recorder.record(Self::NAME, oper_name, size, || {
+            Self::create(elements, config)
+        })??;
+        let rss_growth =
+            residency::resident_set_size()? as i64 - rss_before as i64;
+        recorder.record_residency(&this, oper_name, size)?;
+        recorder.record_footprint(&this, oper_name, size, rss_growth)?;
+        recorder.record_height(&this, size)?;
         Ok(this)
     }
 
     fn record_find<W>(
-        &self,
-        target_elements: &[Element],
-        all_elements: &[Element],
-        mode_name: &str,
+        &mut self,
+        target_elements: &[E],
+        all_elements: &[E],
         oper_name: &str,
-        csv_writer: &mut csv::Writer<W>,
+        recorder: &mut Recorder<W>,
     ) -> io::Result<bool>
     where
         W: io::Write,
     {
-        let then = Instant::now();
-        let mut found_all = true;
-        for &element in target_elements {
-            found_all &= self.find(element);
-        }
-        let elapsed = then.elapsed();
-        let row = RecordRow {
-            mode: mode_name,
-            size: all_elements.len() * mem::size_of::<Element>(),
-            operation: oper_name,
-            collection: Self::NAME,
-            nanoseconds: elapsed.as_nanos(),
-        };
-        csv_writer.serialize(row)?;
+        let size = mem::size_of_val(all_elements);
+        recorder.prepare_cache(|pages| self.storage(pages))?;
+        let found_all = recorder.record(Self::NAME, oper_name, size, || {
+            let mut found_all = true;
+            for &element in target_elements {
+                found_all &= self.find(element);
+            }
+            found_all
+        })?;
+        recorder.record_residency(self, oper_name, size)?;
         Ok(found_all)
     }
 
     fn record_inc_less_than<W>(
         &mut self,
-        target_elements: &[Element],
-        all_elements: &[Element],
-        mode_name: &str,
+        target_elements: &[E],
+        all_elements: &[E],
         oper_name: &str,
-        csv_writer: &mut csv::Writer<W>,
+        recorder: &mut Recorder<W>,
     ) -> io::Result<()>
     where
         W: io::Write,
     {
-        let then = Instant::now();
-        for &element in target_elements {
-            self.inc_less_than(element);
-        }
-        let elapsed = then.elapsed();
-        let row = RecordRow {
-            mode: mode_name,
-            size: all_elements.len() * mem::size_of::<Element>(),
-            operation: oper_name,
-            collection: Self::NAME,
-            nanoseconds: elapsed.as_nanos(),
-        };
-        csv_writer.serialize(row)?;
-        Ok(())
+        let size = mem::size_of_val(all_elements);
+        recorder.prepare_cache(|pages| self.storage(pages))?;
+        recorder.record(Self::NAME, oper_name, size, || {
+            for &element in target_elements {
+                self.inc_less_than(element);
+            }
+        })?;
+        recorder.record_residency(self, oper_name, size)
+    }
+
+    fn record_clone<W>(
+        &self,
+        all_elements: &[E],
+        oper_name: &str,
+        recorder: &mut Recorder<W>,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        let size = mem::size_of_val(all_elements);
+        recorder.prepare_cache(|pages| self.storage(pages))?;
+        let cloned =
+            recorder.record(Self::NAME, oper_name, size, || self.clone())?;
+        recorder.record_residency(&cloned, oper_name, size)
+    }
+
+    fn record_fork_cow<W>(
+        &mut self,
+        target_elements: &[E],
+        all_elements: &[E],
+        oper_name: &str,
+        recorder: &mut Recorder<W>,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        let size = mem::size_of_val(all_elements);
+        recorder.prepare_cache(|pages| self.storage(pages))?;
+        recorder.record_fork(Self::NAME, oper_name, size, || {
+            for &element in target_elements {
+                self.inc_less_than(element);
+            }
+        })
+    }
+}
+
+// Nodes taken from the pool point into it, so collections keep it in a field
+// declared after the one holding their nodes, which drops it last.
+fn create_pool(
+    config: &Config,
+    node_layout: Layout,
+    nodes: usize,
+    placement: Option<Placement>,
+) -> io::Result<Option<Pool>> {
+    if config.node_pool.is_none() && placement.is_none() {
+        return Ok(None);
     }
+    let options = config.node_pool.clone().unwrap_or_default();
+    let placement = placement.unwrap_or(Placement::Sequential);
+    let pool = Pool::with_placement(
+        node_layout,
+        nodes,
+        &options,
+        placement,
+        config.seed_for("node-pool"),
+    )
+    .map_err(|error| {
+        io::Error::new(
+            error.kind(),
+            format!("cannot map node pool: {}", error),
+        )
+    })?;
+    Ok(Some(pool))
 }
 
 #[derive(Debug, Clone)]
-pub struct SortedArray {
-    array_impl: ArrayImpl,
+pub struct SortedArray<E>
+where
+    E: Element,
+{
+    array_impl: ArrayImpl<E>,
 }
 
-impl Collection for SortedArray {
+impl<E> Collection<E> for SortedArray<E>
+where
+    E: Element,
+{
     const NAME: &'static str = "sorted-array";
 
-    fn create(elements: &[Element]) -> Self {
-        let mut array = ArrayImpl::empty();
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
+        let mut array = ArrayImpl::with_config(config)?;
         for &element in elements {
-            array.append(element);
+            array.append(element)?;
         }
         array.sort();
-        Self { array_impl: array }
+        Ok(Self { array_impl: array })
     }
 
-    fn find(&self, element: Element) -> bool {
+    fn find(&mut self, element: E) -> bool {
         self.array_impl.find_sorted(element)
     }
 
-    fn inc_less_than(&mut self, element: Element) {
+    fn inc_less_than(&mut self, element: E) {
         self.array_impl.inc_less_than_sorted(element)
     }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.array_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.array_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.array_impl.into_iter().count()
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct GoodLocalArray {
-    array_impl: ArrayImpl,
+pub struct GoodLocalArray<E>
+where
+    E: Element,
+{
+    array_impl: ArrayImpl<E>,
 }
 
-impl Collection for GoodLocalArray {
+impl<E> Collection<E> for GoodLocalArray<E>
+where
+    E: Element,
+{
     const NAME: &'static str = "good-local-array";
 
-    fn create(elements: &[Element]) -> Self {
-        let mut array = ArrayImpl::empty();
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
+        let mut array = ArrayImpl::with_config(config)?;
         for &element in elements {
-            array.append(element);
+            array.append(element)?;
         }
-        Self { array_impl: array }
+        Ok(Self { array_impl: array })
     }
 
-    fn find(&self, element: Element) -> bool {
+    fn find(&mut self, element: E) -> bool {
         self.array_impl.find_good_local(element)
     }
 
-    fn inc_less_than(&mut self, element: Element) {
+    fn inc_less_than(&mut self, element: E) {
         self.array_impl.inc_less_than_good_local(element)
     }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.array_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.array_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.array_impl.into_iter().count()
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct BadLocalArray {
-    array_impl: ArrayImpl,
+pub struct BadLocalArray<E>
+where
+    E: Element,
+{
+    array_impl: ArrayImpl<E>,
 }
 
-impl Collection for BadLocalArray {
+impl<E> Collection<E> for BadLocalArray<E>
+where
+    E: Element,
+{
     const NAME: &'static str = "bad-local-array";
 
-    fn create(elements: &[Element]) -> Self {
-        let mut array = ArrayImpl::empty();
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
+        let mut array = ArrayImpl::with_config(config)?;
         for &element in elements {
-            array.append(element);
+            array.append(element)?;
         }
-        Self { array_impl: array }
+        Ok(Self { array_impl: array })
     }
 
-    fn find(&self, element: Element) -> bool {
+    fn find(&mut self, element: E) -> bool {
         self.array_impl.find_bad_local(element)
     }
 
-    fn inc_less_than(&mut self, element: Element) {
+    fn inc_less_than(&mut self, element: E) {
         self.array_impl.inc_less_than_bad_local(element)
     }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.array_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.array_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.array_impl.into_iter().count()
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct WorseLocalArray {
-    array_impl: ArrayImpl,
+pub struct WorseLocalArray<E>
+where
+    E: Element,
+{
+    array_impl: ArrayImpl<E>,
 }
 
-impl Collection for WorseLocalArray {
+impl<E> Collection<E> for WorseLocalArray<E>
+where
+    E: Element,
+{
     const NAME: &'static str = "worse-local-array";
 
-    fn create(elements: &[Element]) -> Self {
-        let mut array = ArrayImpl::empty();
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
+        let mut array = ArrayImpl::with_config(config)?;
         for &element in elements {
-            array.append(element);
+            array.append(element)?;
         }
-        Self { array_impl: array }
+        Ok(Self { array_impl: array })
     }
 
-    fn find(&self, element: Element) -> bool {
+    fn find(&mut self, element: E) -> bool {
         self.array_impl.find_worse_local(element)
     }
 
-    fn inc_less_than(&mut self, element: Element) {
+    fn inc_less_than(&mut self, element: E) {
         self.array_impl.inc_less_than_worse_local(element)
     }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.array_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.array_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.array_impl.into_iter().count()
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct LinkedList {
-    list_impl: LinkedListImpl,
+pub struct LinkedList<E>
+where
+    E: Element,
+{
+    list_impl: LinkedListImpl<E>,
+    _pool: Option<Rc<Pool>>,
 }
 
-impl Collection for LinkedList {
+impl<E> Collection<E> for LinkedList<E>
+where
+    E: Element,
+{
     const NAME: &'static str = "linked-list";
 
-    fn create(elements: &[Element]) -> Self {
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
         let mut list = LinkedListImpl::empty();
+        let node_layout = LinkedListImpl::<E>::node_layout();
+        let mut pool = create_pool(
+            config,
+            node_layout,
+            elements.len(),
+            config.node_placement,
+        )?;
         for &element in elements {
-            list.prepend(element);
+            match &mut pool {
+                Some(pool) => unsafe { list.prepend_in(element, pool) },
+                None => list.prepend(element),
+            }
         }
-        Self { list_impl: list }
+        Ok(Self { list_impl: list, _pool: pool.map(Rc::new) })
     }
 
-    fn find(&self, element: Element) -> bool {
+    fn find(&mut self, element: E) -> bool {
         self.list_impl.find(element)
     }
 
-    fn inc_less_than(&mut self, element: Element) {
+    fn inc_less_than(&mut self, element: E) {
         self.list_impl.inc_less_than(element)
     }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.list_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.list_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.list_impl.into_iter().count()
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct WithOrderTree {
-    tree_impl: TreeImpl,
+pub struct WithOrderTree<E>
+where
+    E: Element,
+{
+    tree_impl: TreeImpl<E>,
+    _pool: Option<Rc<Pool>>,
 }
 
-impl Collection for WithOrderTree {
+impl<E> Collection<E> for WithOrderTree<E>
+where
+    E: Element,
+{
     const NAME: &'static str = "with-order-tree";
 
-    fn create(elements: &[Element]) -> Self {
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
         let mut tree = TreeImpl::empty();
+        let node_layout = TreeImpl::<E>::node_layout();
+        let mut pool =
+            create_pool(config, node_layout, elements.len(), None)?;
         for &element in elements {
-            tree.insert_with_order(element);
+            match &mut pool {
+                Some(pool) => {
+                    unsafe { tree.insert_with_order_in(element, pool) };
+                },
+                None => {
+                    tree.insert_with_order(element);
+                },
+            }
         }
-        Self { tree_impl: tree }
+        Ok(Self { tree_impl: tree, _pool: pool.map(Rc::new) })
     }
 
-    fn find(&self, element: Element) -> bool {
+    fn find(&mut self, element: E) -> bool {
         self.tree_impl.find_with_order(element)
     }
 
-    fn inc_less_than(&mut self, element: Element) {
+    fn inc_less_than(&mut self, element: E) {
         self.tree_impl.inc_less_than_with_order(element)
     }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.tree_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.tree_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.tree_impl.into_iter().count()
+    }
+
+    fn height(&self) -> Option<usize> {
+        Some(self.tree_impl.height())
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct WithoutOrderTree {
-    tree_impl: TreeImpl,
+pub struct WithoutOrderTree<E>
+where
+    E: Element,
+{
+    tree_impl: TreeImpl<E>,
+    _pool: Option<Rc<Pool>>,
 }
 
-impl Collection for WithoutOrderTree {
+impl<E> Collection<E> for WithoutOrderTree<E>
+where
+    E: Element,
+{
     const NAME: &'static str = "without-order-tree";
 
-    fn create(elements: &[Element]) -> Self {
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
         let mut tree = TreeImpl::empty();
+        let node_layout = TreeImpl::<E>::node_layout();
+        let mut pool =
+            create_pool(config, node_layout, elements.len(), None)?;
+        for &element in elements {
+            match &mut pool {
+                Some(pool) => {
+                    unsafe { tree.insert_without_order_in(element, pool) };
+                },
+                None => {
+                    tree.insert_without_order(element);
+                },
+            }
+        }
+        Ok(Self { tree_impl: tree, _pool: pool.map(Rc::new) })
+    }
+
+    fn find(&mut self, element: E) -> bool {
+        self.tree_impl.find_without_order(element)
+    }
+
+    fn inc_less_than(&mut self, element: E) {
+        self.tree_impl.inc_less_than_without_order(element)
+    }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.tree_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.tree_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.tree_impl.into_iter().count()
+    }
+
+    fn height(&self) -> Option<usize> {
+        Some(self.tree_impl.height())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ArenaWithOrderTree<E>
+where
+    E: Element,
+{
+    tree_impl: ArenaTreeImpl<E>,
+}
+
+impl<E> Collection<E> for ArenaWithOrderTree<E>
+where
+    E: Element,
+{
+    const NAME: &'static str = "arena-with-order-tree";
+
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
+        let mut tree = ArenaTreeImpl::empty();
+        for &element in elements {
+            tree.insert_with_order(element);
+        }
+        tree.relayout(config.tree_layout);
+        Ok(Self { tree_impl: tree })
+    }
+
+    fn find(&mut self, element: E) -> bool {
+        self.tree_impl.find_with_order(element)
+    }
+
+    fn inc_less_than(&mut self, element: E) {
+        self.tree_impl.inc_less_than_with_order(element)
+    }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.tree_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.tree_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.tree_impl.into_iter().count()
+    }
+
+    fn height(&self) -> Option<usize> {
+        Some(self.tree_impl.height())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ArenaWithoutOrderTree<E>
+where
+    E: Element,
+{
+    tree_impl: ArenaTreeImpl<E>,
+}
+
+impl<E> Collection<E> for ArenaWithoutOrderTree<E>
+where
+    E: Element,
+{
+    const NAME: &'static str = "arena-without-order-tree";
+
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
+        let mut tree = ArenaTreeImpl::empty();
         for &element in elements {
             tree.insert_without_order(element);
         }
-        Self { tree_impl: tree }
+        tree.relayout(config.tree_layout);
+        Ok(Self { tree_impl: tree })
     }
 
-    fn find(&self, element: Element) -> bool {
+    fn find(&mut self, element: E) -> bool {
         self.tree_impl.find_without_order(element)
     }
 
-    fn inc_less_than(&mut self, element: Element) {
+    fn inc_less_than(&mut self, element: E) {
         self.tree_impl.inc_less_than_without_order(element)
     }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.tree_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.tree_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.tree_impl.into_iter().count()
+    }
+
+    fn height(&self) -> Option<usize> {
+        Some(self.tree_impl.height())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BalancedTree<E>
+where
+    E: Element,
+{
+    tree_impl: BalancedTreeImpl<E>,
+    _pool: Option<Rc<Pool>>,
+}
+
+impl<E> Collection<E> for BalancedTree<E>
+where
+    E: Element,
+{
+    const NAME: &'static str = "balanced-tree";
+
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
+        let mut tree = BalancedTreeImpl::empty();
+        let node_layout = BalancedTreeImpl::<E>::node_layout();
+        let mut pool =
+            create_pool(config, node_layout, elements.len(), None)?;
+        for &element in elements {
+            match &mut pool {
+                Some(pool) => {
+                    unsafe { tree.insert_in(element, pool) };
+                },
+                None => {
+                    tree.insert(element);
+                },
+            }
+        }
+        Ok(Self { tree_impl: tree, _pool: pool.map(Rc::new) })
+    }
+
+    fn find(&mut self, element: E) -> bool {
+        self.tree_impl.find(element)
+    }
+
+    fn inc_less_than(&mut self, element: E) {
+        self.tree_impl.inc_less_than(element)
+    }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.tree_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.tree_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.tree_impl.into_iter().count()
+    }
+
+    fn height(&self) -> Option<usize> {
+        Some(self.tree_impl.height())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BTree<E>
+where
+    E: Element,
+{
+    tree_impl: BTreeImpl<E>,
+    _pool: Option<Rc<Pool>>,
+}
+
+impl<E> Collection<E> for BTree<E>
+where
+    E: Element,
+{
+    const NAME: &'static str = "b-tree";
+
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
+        let mut tree = BTreeImpl::new(config.btree_node_size);
+        // Leaves split in halves, and there are fewer inner nodes than
+        // leaves.
+        let leaves = elements.len().div_ceil(tree.leaf_capacity() / 2);
+        let mut pool =
+            create_pool(config, tree.node_layout(), leaves * 2, None)?;
+        for &element in elements {
+            match &mut pool {
+                Some(pool) => unsafe { tree.insert_in(element, pool) },
+                None => tree.insert(element),
+            }
+        }
+        Ok(Self { tree_impl: tree, _pool: pool.map(Rc::new) })
+    }
+
+    fn find(&mut self, element: E) -> bool {
+        self.tree_impl.find(element)
+    }
+
+    fn inc_less_than(&mut self, element: E) {
+        self.tree_impl.inc_less_than(element)
+    }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.tree_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.tree_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.tree_impl.into_iter().count()
+    }
+
+    fn height(&self) -> Option<usize> {
+        Some(self.tree_impl.height())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EytzingerArray<E>
+where
+    E: Element,
+{
+    array_impl: EytzingerArrayImpl<E>,
+}
+
+impl<E> Collection<E> for EytzingerArray<E>
+where
+    E: Element,
+{
+    const NAME: &'static str = "eytzinger-array";
+
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
+        let mut sorted = elements.to_vec();
+        sorted.sort();
+        let array = EytzingerArrayImpl::from_sorted(&sorted, config.prefetch);
+        Ok(Self { array_impl: array })
+    }
+
+    fn find(&mut self, element: E) -> bool {
+        self.array_impl.find(element)
+    }
+
+    fn inc_less_than(&mut self, element: E) {
+        self.array_impl.inc_less_than(element)
+    }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.array_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.array_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.array_impl.into_iter().count()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VebArray<E>
+where
+    E: Element,
+{
+    array_impl: VebArrayImpl<E>,
+}
+
+impl<E> Collection<E> for VebArray<E>
+where
+    E: Element,
+{
+    const NAME: &'static str = "veb-array";
+
+    fn create(elements: &[E], _config: &Config) -> io::Result<Self> {
+        let mut sorted = elements.to_vec();
+        sorted.sort();
+        Ok(Self { array_impl: VebArrayImpl::from_sorted(&sorted) })
+    }
+
+    fn find(&mut self, element: E) -> bool {
+        self.array_impl.find(element)
+    }
+
+    fn inc_less_than(&mut self, element: E) {
+        self.array_impl.inc_less_than(element)
+    }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.array_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.array_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.array_impl.into_iter().count()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OpenTable<E>
+where
+    E: Element,
+{
+    table_impl: OpenTableImpl<E>,
+}
+
+impl<E> Collection<E> for OpenTable<E>
+where
+    E: Element,
+{
+    const NAME: &'static str = "open-table";
+
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
+        let mut table = OpenTableImpl::new(config.open_load_factor);
+        for &element in elements {
+            table.insert(element);
+        }
+        Ok(Self { table_impl: table })
+    }
+
+    fn find(&mut self, element: E) -> bool {
+        self.table_impl.find(element)
+    }
+
+    fn inc_less_than(&mut self, element: E) {
+        self.table_impl.inc_less_than(element)
+    }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.table_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.table_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.table_impl.into_iter().count()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChainedTable<E>
+where
+    E: Element,
+{
+    table_impl: ChainedTableImpl<E>,
+    _pool: Option<Rc<Pool>>,
+}
+
+impl<E> Collection<E> for ChainedTable<E>
+where
+    E: Element,
+{
+    const NAME: &'static str = "chained-table";
+
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
+        let mut table = ChainedTableImpl::new(config.chained_load_factor);
+        let node_layout = ChainedTableImpl::<E>::node_layout();
+        let mut pool =
+            create_pool(config, node_layout, elements.len(), None)?;
+        for &element in elements {
+            match &mut pool {
+                Some(pool) => unsafe { table.insert_in(element, pool) },
+                None => table.insert(element),
+            }
+        }
+        Ok(Self { table_impl: table, _pool: pool.map(Rc::new) })
+    }
+
+    fn find(&mut self, element: E) -> bool {
+        self.table_impl.find(element)
+    }
+
+    fn inc_less_than(&mut self, element: E) {
+        self.table_impl.inc_less_than(element)
+    }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.table_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.table_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.table_impl.into_iter().count()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SkipList<E>
+where
+    E: Element,
+{
+    list_impl: SkipListImpl<E>,
+    _pool: Option<Rc<Pool>>,
+}
+
+impl<E> Collection<E> for SkipList<E>
+where
+    E: Element,
+{
+    const NAME: &'static str = "skip-list";
+
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
+        let mut list = SkipListImpl::new(config.seed_for("skip-list"));
+        let node_layout = SkipListImpl::<E>::node_layout();
+        let mut pool =
+            create_pool(config, node_layout, elements.len(), None)?;
+        for &element in elements {
+            match &mut pool {
+                Some(pool) => unsafe { list.insert_in(element, pool) },
+                None => list.insert(element),
+            }
+        }
+        Ok(Self { list_impl: list, _pool: pool.map(Rc::new) })
+    }
+
+    fn find(&mut self, element: E) -> bool {
+        self.list_impl.find(element)
+    }
+
+    fn inc_less_than(&mut self, element: E) {
+        self.list_impl.inc_less_than(element)
+    }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.list_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.list_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.list_impl.into_iter().count()
+    }
+
+    fn height(&self) -> Option<usize> {
+        Some(self.list_impl.height())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnrolledList<E>
+where
+    E: Element,
+{
+    list_impl: UnrolledListImpl<E>,
+    _pool: Option<Rc<Pool>>,
+}
+
+impl<E> Collection<E> for UnrolledList<E>
+where
+    E: Element,
+{
+    const NAME: &'static str = "unrolled-list";
+
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
+        let mut list = UnrolledListImpl::new(config.unrolled_node_size);
+        let nodes = elements.len().div_ceil(list.node_capacity());
+        let mut pool = create_pool(
+            config,
+            list.node_layout(),
+            nodes,
+            config.node_placement,
+        )?;
+        for &element in elements {
+            match &mut pool {
+                Some(pool) => unsafe { list.prepend_in(element, pool) },
+                None => list.prepend(element),
+            }
+        }
+        Ok(Self { list_impl: list, _pool: pool.map(Rc::new) })
+    }
+
+    fn find(&mut self, element: E) -> bool {
+        self.list_impl.find(element)
+    }
+
+    fn inc_less_than(&mut self, element: E) {
+        self.list_impl.inc_less_than(element)
+    }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.list_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.list_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.list_impl.into_iter().count()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MoveToFrontList<E>
+where
+    E: Element,
+{
+    list_impl: SelfOrganizingList<E>,
+    _pool: Option<Rc<Pool>>,
+}
+
+impl<E> Collection<E> for MoveToFrontList<E>
+where
+    E: Element,
+{
+    const NAME: &'static str = "move-to-front-list";
+
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
+        let mut list = SelfOrganizingList::empty(Heuristic::MoveToFront);
+        let node_layout = SelfOrganizingList::<E>::node_layout();
+        let mut pool = create_pool(
+            config,
+            node_layout,
+            elements.len(),
+            config.node_placement,
+        )?;
+        for &element in elements {
+            match &mut pool {
+                Some(pool) => unsafe { list.prepend_in(element, pool) },
+                None => list.prepend(element),
+            }
+        }
+        Ok(Self { list_impl: list, _pool: pool.map(Rc::new) })
+    }
+
+    fn find(&mut self, element: E) -> bool {
+        self.list_impl.find(element)
+    }
+
+    fn inc_less_than(&mut self, element: E) {
+        self.list_impl.inc_less_than(element)
+    }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.list_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.list_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.list_impl.into_iter().count()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TransposeList<E>
+where
+    E: Element,
+{
+    list_impl: SelfOrganizingList<E>,
+    _pool: Option<Rc<Pool>>,
+}
+
+impl<E> Collection<E> for TransposeList<E>
+where
+    E: Element,
+{
+    const NAME: &'static str = "transpose-list";
+
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
+        let mut list = SelfOrganizingList::empty(Heuristic::Transpose);
+        let node_layout = SelfOrganizingList::<E>::node_layout();
+        let mut pool = create_pool(
+            config,
+            node_layout,
+            elements.len(),
+            config.node_placement,
+        )?;
+        for &element in elements {
+            match &mut pool {
+                Some(pool) => unsafe { list.prepend_in(element, pool) },
+                None => list.prepend(element),
+            }
+        }
+        Ok(Self { list_impl: list, _pool: pool.map(Rc::new) })
+    }
+
+    fn find(&mut self, element: E) -> bool {
+        self.list_impl.find(element)
+    }
+
+    fn inc_less_than(&mut self, element: E) {
+        self.list_impl.inc_less_than(element)
+    }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.list_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.list_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.list_impl.into_iter().count()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DoublyLinkedList<E>
+where
+    E: Element,
+{
+    list_impl: DoublyLinkedListImpl<E>,
+    _pool: Option<Rc<Pool>>,
+}
+
+impl<E> Collection<E> for DoublyLinkedList<E>
+where
+    E: Element,
+{
+    const NAME: &'static str = "doubly-linked-list";
+
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
+        let mut list = DoublyLinkedListImpl::empty();
+        let node_layout = DoublyLinkedListImpl::<E>::node_layout();
+        let mut pool = create_pool(
+            config,
+            node_layout,
+            elements.len(),
+            config.node_placement,
+        )?;
+        for &element in elements {
+            match &mut pool {
+                Some(pool) => unsafe { list.push_front_in(element, pool) },
+                None => list.push_front(element),
+            }
+        }
+        Ok(Self { list_impl: list, _pool: pool.map(Rc::new) })
+    }
+
+    fn find(&mut self, element: E) -> bool {
+        self.list_impl.find(element)
+    }
+
+    fn inc_less_than(&mut self, element: E) {
+        self.list_impl.inc_less_than(element)
+    }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.list_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.list_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.list_impl.into_iter().count()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct XorLinkedList<E>
+where
+    E: Element,
+{
+    list_impl: XorLinkedListImpl<E>,
+    _pool: Option<Rc<Pool>>,
+}
+
+impl<E> Collection<E> for XorLinkedList<E>
+where
+    E: Element,
+{
+    const NAME: &'static str = "xor-linked-list";
+
+    fn create(elements: &[E], config: &Config) -> io::Result<Self> {
+        let mut list = XorLinkedListImpl::empty();
+        let node_layout = XorLinkedListImpl::<E>::node_layout();
+        let mut pool = create_pool(
+            config,
+            node_layout,
+            elements.len(),
+            config.node_placement,
+        )?;
+        for &element in elements {
+            match &mut pool {
+                Some(pool) => unsafe { list.push_front_in(element, pool) },
+                None => list.push_front(element),
+            }
+        }
+        Ok(Self { list_impl: list, _pool: pool.map(Rc::new) })
+    }
+
+    fn find(&mut self, element: E) -> bool {
+        self.list_impl.find(element)
+    }
+
+    fn inc_less_than(&mut self, element: E) {
+        self.list_impl.inc_less_than(element)
+    }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.list_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.list_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.list_impl.into_iter().count()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FenwickArray<E>
+where
+    E: Element,
+{
+    array_impl: FenwickArrayImpl<E>,
+}
+
+impl<E> Collection<E> for FenwickArray<E>
+where
+    E: Element,
+{
+    const NAME: &'static str = "fenwick-array";
+
+    fn create(elements: &[E], _config: &Config) -> io::Result<Self> {
+        let mut sorted = elements.to_vec();
+        sorted.sort();
+        Ok(Self { array_impl: FenwickArrayImpl::from_sorted(&sorted) })
+    }
+
+    fn find(&mut self, element: E) -> bool {
+        self.array_impl.find(element)
+    }
+
+    fn inc_less_than(&mut self, element: E) {
+        self.array_impl.inc_less_than(element)
+    }
+
+    fn storage(&self, pages: &mut PageSet) {
+        self.array_impl.storage(pages)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.array_impl.allocated_bytes()
+    }
+
+    fn element_count(&self) -> usize {
+        self.array_impl.into_iter().count()
+    }
 }
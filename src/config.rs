@@ -0,0 +1,102 @@
+use crate::{
+    arena_tree::TreeLayout,
+    mmap::MapOptions,
+    pool::Placement,
+    reuse_distance::CACHE_LINE_SIZE,
+    BTREE_NODE_SIZE,
+    CHAINED_LOAD_FACTOR,
+    OPEN_LOAD_FACTOR,
+    PAGE_SIZE,
+};
+
+#[derive(Debug, Clone)]
+pub enum ArrayBacking {
+    Heap,
+    Mapped(MapOptions),
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub array_backing: ArrayBacking,
+    // Page size assumed by the strides of bad and worse locality arrays.
+    pub page_size: usize,
+    // Lists and trees allocate their nodes from a pool mapped with these
+    // options instead of the heap.
+    pub node_pool: Option<MapOptions>,
+    // Linked lists allocate their nodes from a pool placing them this way.
+    pub node_placement: Option<Placement>,
+    // Order of the nodes of arena trees after they are created.
+    pub tree_layout: TreeLayout,
+    // Bytes of each B-tree node, keys and child pointers included.
+    pub btree_node_size: usize,
+    // Bytes of each unrolled linked list node, its header included.
+    pub unrolled_node_size: usize,
+    // Eytzinger array searches prefetch descendants a few levels down.
+    pub prefetch: bool,
+    // Elements per slot above which the open table grows, less than 1.
+    pub open_load_factor: f64,
+    // Elements per bucket above which the chained table grows.
+    pub chained_load_factor: f64,
+    // Lookups of the skewed-find operation, which only runs if there are
+    // any.
+    pub skewed_finds: usize,
+    // Seed of the run, for collections laid out at random.
+    pub seed: [u8; 32],
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            array_backing: ArrayBacking::Heap,
+            page_size: PAGE_SIZE,
+            node_pool: None,
+            node_placement: None,
+            tree_layout: TreeLayout::Insertion,
+            btree_node_size: BTREE_NODE_SIZE,
+            unrolled_node_size: CACHE_LINE_SIZE,
+            prefetch: false,
+            open_load_factor: OPEN_LOAD_FACTOR,
+            chained_load_factor: CHAINED_LOAD_FACTOR,
+            skewed_finds: 0,
+            seed: [0; 32],
+        }
+    }
+}
+
+impl Config {
+    // Seed of a stream of its own for each purpose, so that no collection
+    // draws the same numbers as the input: SplitMix64 of every word of the
+    // run seed, salted with the FNV-1a hash of the purpose.
+    pub fn seed_for(&self, purpose: &str) -> [u8; 32] {
+        let salt = purpose.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100_0000_01b3)
+        });
+        let mut seed = [0; 32];
+        for (word, run_word) in seed.chunks_mut(8).zip(self.seed.chunks(8)) {
+            let run_word = u64::from_le_bytes(run_word.try_into().unwrap());
+            word.copy_from_slice(&split_mix(run_word ^ salt).to_le_bytes());
+        }
+        seed
+    }
+}
+
+fn split_mix(word: u64) -> u64 {
+    let mut mixed = word.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    mixed ^ (mixed >> 31)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Config;
+
+    #[test]
+    fn seeds_for_purposes() {
+        let config = Config::default();
+        let skip_list = config.seed_for("skip-list");
+        assert_eq!(skip_list, config.seed_for("skip-list"));
+        assert_ne!(skip_list, config.seed);
+        assert_ne!(skip_list, config.seed_for("node-pool"));
+    }
+}
@@ -0,0 +1,336 @@
+use crate::{
+    element::Element,
+    pool::{NodeBox, NodePtr, Pool},
+    residency::PageSet,
+    trace,
+};
+use std::{alloc::Layout, marker::PhantomData};
+
+type Link<E> = Option<NodePtr<Node<E>>>;
+
+#[derive(Debug)]
+struct Node<E> {
+    data: E,
+    prev: Link<E>,
+    next: Link<E>,
+}
+
+// Linked list with a link to the previous node besides the next one.
+#[derive(Debug)]
+pub struct DoublyLinkedList<E> {
+    head: Link<E>,
+    tail: Link<E>,
+    len: usize,
+}
+
+impl<E> Default for DoublyLinkedList<E>
+where
+    E: Element,
+{
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<E> DoublyLinkedList<E>
+where
+    E: Element,
+{
+    pub fn empty() -> Self {
+        Self { head: None, tail: None, len: 0 }
+    }
+
+    pub fn node_layout() -> Layout {
+        Layout::new::<Node<E>>()
+    }
+
+    pub fn push_front(&mut self, element: E) {
+        self.push_front_with(element, NodeBox::new);
+    }
+
+    // The pool must outlive the list.
+    pub unsafe fn push_front_in(&mut self, element: E, pool: &mut Pool) {
+        self.push_front_with(element, |node| pool.alloc(node));
+    }
+
+    fn push_front_with<F>(&mut self, element: E, alloc: F)
+    where
+        F: FnOnce(Node<E>) -> NodeBox<Node<E>>,
+    {
+        let node = Node { data: element, prev: None, next: self.head };
+        let node = alloc(node).into_node_ptr();
+        match self.head {
+            Some(head) => unsafe { (*head.as_ptr()).prev = Some(node) },
+            None => self.tail = Some(node),
+        }
+        self.head = Some(node);
+        self.len += 1;
+        trace::touch(unsafe { node.as_ref() });
+    }
+
+    pub fn push_back(&mut self, element: E) {
+        self.push_back_with(element, NodeBox::new);
+    }
+
+    // The pool must outlive the list.
+    pub unsafe fn push_back_in(&mut self, element: E, pool: &mut Pool) {
+        self.push_back_with(element, |node| pool.alloc(node));
+    }
+
+    fn push_back_with<F>(&mut self, element: E, alloc: F)
+    where
+        F: FnOnce(Node<E>) -> NodeBox<Node<E>>,
+    {
+        let node = Node { data: element, prev: self.tail, next: None };
+        let node = alloc(node).into_node_ptr();
+        match self.tail {
+            Some(tail) => unsafe { (*tail.as_ptr()).next = Some(node) },
+            None => self.head = Some(node),
+        }
+        self.tail = Some(node);
+        self.len += 1;
+        trace::touch(unsafe { node.as_ref() });
+    }
+
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, E> {
+        CursorMut { current: self.head, list: self }
+    }
+
+    // Frees the node, which must be in the list.
+    fn unlink(&mut self, node: NodePtr<Node<E>>) -> E {
+        let node = unsafe { node.into_box() };
+        match node.prev {
+            Some(prev) => unsafe { (*prev.as_ptr()).next = node.next },
+            None => self.head = node.next,
+        }
+        match node.next {
+            Some(next) => unsafe { (*next.as_ptr()).prev = node.prev },
+            None => self.tail = node.prev,
+        }
+        self.len -= 1;
+        node.data
+    }
+
+    pub fn find(&self, element: E) -> bool {
+        let mut link = self.head;
+        while let Some(node) = link {
+            let node = unsafe { node.as_ref() };
+            trace::touch(node);
+            if node.data == element {
+                return true;
+            }
+            link = node.next;
+        }
+        false
+    }
+
+    pub fn inc_less_than(&mut self, element: E) {
+        let mut link = self.head;
+        while let Some(node) = link {
+            let node = unsafe { &mut *node.as_ptr() };
+            trace::touch(&*node);
+            if node.data < element {
+                node.data = node.data.wrapping_inc();
+            }
+            link = node.next;
+        }
+    }
+
+    pub fn storage(&self, pages: &mut PageSet) {
+        let mut link = self.head;
+        while let Some(node) = link {
+            let node = unsafe { node.as_ref() };
+            pages.insert(node);
+            link = node.next;
+        }
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        let mut link = self.head;
+        let mut bytes = 0;
+        while let Some(node) = link {
+            bytes += node.allocated_size();
+            link = unsafe { node.as_ref() }.next;
+        }
+        bytes
+    }
+}
+
+impl<E> Clone for DoublyLinkedList<E>
+where
+    E: Element,
+{
+    fn clone(&self) -> Self {
+        let mut new_list = Self::empty();
+        for element in self {
+            new_list.push_back(element);
+        }
+        new_list
+    }
+}
+
+impl<E> Drop for DoublyLinkedList<E> {
+    fn drop(&mut self) {
+        let mut link = self.head;
+        while let Some(node) = link {
+            let node = unsafe { node.into_box() };
+            link = node.next;
+        }
+    }
+}
+
+// Points to a node of the list, or past its end.
+#[derive(Debug)]
+pub struct CursorMut<'list, E> {
+    list: &'list mut DoublyLinkedList<E>,
+    current: Link<E>,
+}
+
+impl<'list, E> CursorMut<'list, E>
+where
+    E: Element,
+{
+    pub fn current(&self) -> Option<E> {
+        self.current.map(|node| unsafe { node.as_ref() }.data)
+    }
+
+    pub fn move_next(&mut self) {
+        if let Some(node) = self.current {
+            self.current = unsafe { node.as_ref() }.next;
+        }
+    }
+
+    // Unlinks the current node and moves to the next one.
+    pub fn remove_current(&mut self) -> Option<E> {
+        let node = self.current?;
+        self.current = unsafe { node.as_ref() }.next;
+        Some(self.list.unlink(node))
+    }
+}
+
+impl<'list, E> IntoIterator for &'list DoublyLinkedList<E>
+where
+    E: Element,
+{
+    type Item = E;
+    type IntoIter = Iter<'list, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter {
+            front: self.head,
+            back: self.tail,
+            len: self.len,
+            _list: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Iter<'list, E> {
+    front: Link<E>,
+    back: Link<E>,
+    // Nodes between the front and the back, both included.
+    len: usize,
+    _list: PhantomData<&'list DoublyLinkedList<E>>,
+}
+
+impl<'list, E> Iterator for Iter<'list, E>
+where
+    E: Element,
+{
+    type Item = E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = unsafe { self.front?.as_ref() };
+        self.front = node.next;
+        self.len -= 1;
+        Some(node.data)
+    }
+}
+
+impl<'list, E> DoubleEndedIterator for Iter<'list, E>
+where
+    E: Element,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = unsafe { self.back?.as_ref() };
+        self.back = node.prev;
+        self.len -= 1;
+        Some(node.data)
+    }
+}
+
+// Tests shared with the other doubly linked lists, given the list type.
+#[cfg(test)]
+macro_rules! doubly_linked_tests {
+    ($List:ident) => {
+        use crate::{mmap::MapOptions, pool::Pool};
+
+        #[test]
+        fn both_ends() {
+            let mut list = $List::<u64>::empty();
+            for element in [5, 3, 10] {
+                list.push_front(element);
+            }
+            list.push_back(9);
+            list.push_back(1);
+            assert!(list.into_iter().eq([10, 3, 5, 9, 1]));
+            assert!(list.into_iter().rev().eq([1, 9, 5, 3, 10]));
+            let mut iter = list.into_iter();
+            assert_eq!(iter.next(), Some(10));
+            assert_eq!(iter.next_back(), Some(1));
+            assert!(iter.eq([3, 5, 9]));
+
+            list.inc_less_than(9);
+            assert!(list.find(6) && list.find(10) && !list.find(5));
+            assert!(list.clone().into_iter().eq([10, 4, 6, 9, 2]));
+        }
+
+        #[test]
+        fn remove_with_cursor() {
+            // Too small for every node, so that some come from the heap.
+            let layout = $List::<u64>::node_layout();
+            let mut pool =
+                Pool::new(layout, 3, &MapOptions::default()).unwrap();
+            let mut list = $List::<u64>::empty();
+            for element in 0 .. 6 {
+                unsafe { list.push_back_in(element, &mut pool) };
+            }
+            let mut cursor = list.cursor_front_mut();
+            while let Some(element) = cursor.current() {
+                if element % 2 == 0 {
+                    assert_eq!(cursor.remove_current(), Some(element));
+                } else {
+                    cursor.move_next();
+                }
+            }
+            assert!(list.into_iter().eq([1, 3, 5]));
+            assert!(list.into_iter().rev().eq([5, 3, 1]));
+
+            let mut cursor = list.cursor_front_mut();
+            for _ in 0 .. 3 {
+                cursor.remove_current();
+            }
+            assert_eq!(cursor.remove_current(), None);
+            assert!(list.into_iter().next().is_none());
+            unsafe { list.push_front_in(7, &mut pool) };
+            assert!(list.into_iter().rev().eq([7]));
+        }
+    };
+}
+
+#[cfg(test)]
+pub(crate) use doubly_linked_tests;
+
+#[cfg(test)]
+mod test {
+    use super::DoublyLinkedList;
+
+    super::doubly_linked_tests!(DoublyLinkedList);
+}
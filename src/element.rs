@@ -0,0 +1,313 @@
+use crate::PAGE_SIZE;
+use rand::Rng;
+use std::{cmp, error::Error, fmt, mem, str::FromStr};
+
+#[derive(Debug, Clone)]
+pub struct ElementTypeError;
+
+impl fmt::Display for ElementTypeError {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmtr, "Element type must be one of u32, u64, u128 or record")
+    }
+}
+
+impl Error for ElementTypeError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementType {
+    U32,
+    U64,
+    U128,
+    Record,
+}
+
+impl ElementType {
+    pub fn name(self) -> &'static str {
+        match self {
+            ElementType::U32 => "u32",
+            ElementType::U64 => "u64",
+            ElementType::U128 => "u128",
+            ElementType::Record => "record",
+        }
+    }
+}
+
+impl FromStr for ElementType {
+    type Err = ElementTypeError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "u32" => Ok(ElementType::U32),
+            "u64" => Ok(ElementType::U64),
+            "u128" => Ok(ElementType::U128),
+            "record" => Ok(ElementType::Record),
+            _ => Err(ElementTypeError),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DistributionError;
+
+impl fmt::Display for DistributionError {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmtr,
+            "Input distribution must be one of random, sorted or nearly-sorted"
+        )
+    }
+}
+
+impl Error for DistributionError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Distribution {
+    Random,
+    Sorted,
+    // Sorted, then with one in a hundred elements swapped with another.
+    NearlySorted,
+}
+
+impl Distribution {
+    pub fn name(self) -> &'static str {
+        match self {
+            Distribution::Random => "random",
+            Distribution::Sorted => "sorted",
+            Distribution::NearlySorted => "nearly-sorted",
+        }
+    }
+
+    pub fn arrange<E, R>(self, elements: &mut [E], rng: &mut R)
+    where
+        E: Element,
+        R: Rng,
+    {
+        if self == Distribution::Random {
+            return;
+        }
+        elements.sort();
+        if self == Distribution::NearlySorted && !elements.is_empty() {
+            for _ in 0 .. elements.len().div_ceil(100) {
+                let first = rng.gen_range(0 .. elements.len());
+                let second = rng.gen_range(0 .. elements.len());
+                elements.swap(first, second);
+            }
+        }
+    }
+}
+
+impl FromStr for Distribution {
+    type Err = DistributionError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "random" => Ok(Distribution::Random),
+            "sorted" => Ok(Distribution::Sorted),
+            "nearly-sorted" => Ok(Distribution::NearlySorted),
+            _ => Err(DistributionError),
+        }
+    }
+}
+
+pub trait Element: Copy + Ord + fmt::Debug + 'static {
+    const MAX: Self;
+    const ELEMS_IN_PAGE: usize = PAGE_SIZE / mem::size_of::<Self>();
+
+    fn random<R>(rng: &mut R) -> Self
+    where
+        R: Rng;
+
+    fn fill_random<R>(elements: &mut [Self], rng: &mut R)
+    where
+        R: Rng,
+    {
+        for element in elements {
+            *element = Self::random(rng);
+        }
+    }
+
+    fn wrapping_inc(self) -> Self;
+
+    fn wrapping_inc_by(self, count: u64) -> Self;
+
+    // Bits of the element mixed by hash tables.
+    fn hash_bits(self) -> u64;
+}
+
+impl Element for u32 {
+    const MAX: Self = u32::MAX;
+
+    fn random<R>(rng: &mut R) -> Self
+    where
+        R: Rng,
+    {
+        rng.gen()
+    }
+
+    fn fill_random<R>(elements: &mut [Self], rng: &mut R)
+    where
+        R: Rng,
+    {
+        rng.fill(elements);
+    }
+
+    fn wrapping_inc(self) -> Self {
+        self.wrapping_add(1)
+    }
+
+    fn wrapping_inc_by(self, count: u64) -> Self {
+        self.wrapping_add(count as u32)
+    }
+
+    fn hash_bits(self) -> u64 {
+        self as u64
+    }
+}
+
+impl Element for u64 {
+    const MAX: Self = u64::MAX;
+
+    fn random<R>(rng: &mut R) -> Self
+    where
+        R: Rng,
+    {
+        rng.gen()
+    }
+
+    fn fill_random<R>(elements: &mut [Self], rng: &mut R)
+    where
+        R: Rng,
+    {
+        rng.fill(elements);
+    }
+
+    fn wrapping_inc(self) -> Self {
+        self.wrapping_add(1)
+    }
+
+    fn wrapping_inc_by(self, count: u64) -> Self {
+        self.wrapping_add(count)
+    }
+
+    fn hash_bits(self) -> u64 {
+        self
+    }
+}
+
+impl Element for u128 {
+    const MAX: Self = u128::MAX;
+
+    fn random<R>(rng: &mut R) -> Self
+    where
+        R: Rng,
+    {
+        rng.gen()
+    }
+
+    fn fill_random<R>(elements: &mut [Self], rng: &mut R)
+    where
+        R: Rng,
+    {
+        rng.fill(elements);
+    }
+
+    fn wrapping_inc(self) -> Self {
+        self.wrapping_add(1)
+    }
+
+    fn wrapping_inc_by(self, count: u64) -> Self {
+        self.wrapping_add(count as u128)
+    }
+
+    fn hash_bits(self) -> u64 {
+        (self ^ self >> 64) as u64
+    }
+}
+
+// A key padded to a whole cache line. Only the key is compared.
+#[derive(Debug, Clone, Copy)]
+#[repr(C, align(64))]
+pub struct Record {
+    pub key: u64,
+}
+
+impl PartialEq for Record {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for Record {}
+
+impl PartialOrd for Record {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Record {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+impl Element for Record {
+    const MAX: Self = Record { key: u64::MAX };
+
+    fn random<R>(rng: &mut R) -> Self
+    where
+        R: Rng,
+    {
+        Record { key: rng.gen() }
+    }
+
+    fn wrapping_inc(self) -> Self {
+        Record { key: self.key.wrapping_add(1) }
+    }
+
+    fn wrapping_inc_by(self, count: u64) -> Self {
+        Record { key: self.key.wrapping_add(count) }
+    }
+
+    fn hash_bits(self) -> u64 {
+        self.key
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Distribution, Element, Record};
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::mem;
+
+    #[test]
+    fn elems_in_page() {
+        assert_eq!(u32::ELEMS_IN_PAGE, 1024);
+        assert_eq!(u64::ELEMS_IN_PAGE, 512);
+        assert_eq!(u128::ELEMS_IN_PAGE, 256);
+        assert_eq!(mem::size_of::<Record>(), 64);
+        assert_eq!(Record::ELEMS_IN_PAGE, 64);
+    }
+
+    #[test]
+    fn distributions() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut random = vec![0u64; 1000];
+        u64::fill_random(&mut random, &mut rng);
+
+        let mut sorted = random.clone();
+        Distribution::Sorted.arrange(&mut sorted, &mut rng);
+        assert!(sorted.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        let mut nearly_sorted = random.clone();
+        Distribution::NearlySorted.arrange(&mut nearly_sorted, &mut rng);
+        let misplaced = nearly_sorted
+            .iter()
+            .zip(&sorted)
+            .filter(|(element, sorted)| element != sorted)
+            .count();
+        assert!(misplaced <= 2 * 10);
+        nearly_sorted.sort();
+        assert_eq!(nearly_sorted, sorted);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::mmap::THP_DIR;
+use std::{
+    env,
+    fs,
+    io,
+    mem,
+    os::unix::process::CommandExt,
+    process::Command,
+};
+
+const CPU_DIR: &str = "/sys/devices/system/cpu";
+
+pub fn pin_to_cpu(cpu: usize) -> io::Result<()> {
+    if cpu >= libc::CPU_SETSIZE as usize {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("cpu {} is out of range", cpu),
+        ))?;
+    }
+    let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+    unsafe { libc::CPU_SET(cpu, &mut set) };
+    if unsafe { libc::sched_setaffinity(0, mem::size_of_val(&set), &set) } < 0
+    {
+        Err(io::Error::last_os_error())?;
+    }
+    Ok(())
+}
+
+pub fn use_fifo(priority: i32) -> io::Result<()> {
+    let param = libc::sched_param { sched_priority: priority };
+    if unsafe { libc::sched_setscheduler(0, libc::SCHED_FIFO, &param) } < 0 {
+        Err(io::Error::last_os_error())?;
+    }
+    Ok(())
+}
+
+pub fn set_nice(nice: i32) -> io::Result<()> {
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } < 0 {
+        Err(io::Error::last_os_error())?;
+    }
+    Ok(())
+}
+
+fn personality() -> io::Result<libc::c_int> {
+    let persona = unsafe { libc::personality(0xffffffff) };
+    if persona < 0 {
+        Err(io::Error::last_os_error())?;
+    }
+    Ok(persona)
+}
+
+pub fn is_aslr_disabled() -> bool {
+    let by_persona = personality()
+        .is_ok_and(|persona| persona & libc::ADDR_NO_RANDOMIZE != 0);
+    let by_system = fs::read_to_string("/proc/sys/kernel/randomize_va_space")
+        .is_ok_and(|randomize| randomize.trim() == "0");
+    by_persona || by_system
+}
+
+// Only returns if ASLR is already disabled for this process, since the new
+// personality only takes effect on exec.
+pub fn disable_aslr() -> io::Result<()> {
+    let persona = personality()?;
+    if persona & libc::ADDR_NO_RANDOMIZE != 0 {
+        return Ok(());
+    }
+    let persona = (persona | libc::ADDR_NO_RANDOMIZE) as libc::c_ulong;
+    if unsafe { libc::personality(persona) } < 0 {
+        Err(io::Error::last_os_error())?;
+    }
+    Err(Command::new(env::current_exe()?)
+        .args(env::args_os().skip(1))
+        .exec())
+}
+
+// Selected value of a sysfs setting such as `always [madvise] never`.
+fn selected_value(setting: &str) -> Option<&str> {
+    let start = setting.find('[')? + 1;
+    let end = start + setting[start ..].find(']')?;
+    Some(&setting[start .. end])
+}
+
+pub fn thp_mode() -> Option<String> {
+    let enabled = fs::read_to_string(THP_DIR.to_owned() + "/enabled").ok()?;
+    selected_value(&enabled).map(str::to_owned)
+}
+
+// Frequency governors of the given CPU, or of every CPU that has one.
+pub fn governors(cpu: Option<usize>) -> Vec<String> {
+    let cpus = match cpu {
+        Some(cpu) => vec![cpu],
+        None => fs::read_dir(CPU_DIR)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name().into_string().ok()?;
+                name.strip_prefix("cpu")?.parse().ok()
+            })
+            .collect(),
+    };
+    cpus.into_iter()
+        .filter_map(|cpu| {
+            let path =
+                format!("{}/cpu{}/cpufreq/scaling_governor", CPU_DIR, cpu);
+            fs::read_to_string(path).ok()
+        })
+        .map(|governor| governor.trim().to_owned())
+        .collect()
+}
+
+pub fn noise_sources(cpu: Option<usize>) -> Vec<String> {
+    let mut noise_sources = Vec::new();
+
+    let governors = governors(cpu);
+    let slow = governors
+        .iter()
+        .filter(|&governor| governor != "performance")
+        .count();
+    if slow > 0 {
+        noise_sources.push(format!(
+            "CPU frequency governor is not performance on {} of {} CPUs",
+            slow,
+            governors.len()
+        ));
+    }
+
+    if thp_mode().as_deref() == Some("always") {
+        noise_sources
+            .push("transparent huge pages are set to always".to_owned());
+    }
+
+    noise_sources
+}
+
+#[cfg(test)]
+mod test {
+    use super::selected_value;
+
+    #[test]
+    fn selected() {
+        assert_eq!(selected_value("always [madvise] never\n"), Some("madvise"));
+        assert_eq!(selected_value("[always] madvise never"), Some("always"));
+        assert_eq!(selected_value("always madvise never"), None);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{element::Element, residency::PageSet, reuse_distance, trace};
+use std::mem;
+
+// Sorted elements stored in breadth-first order of an implicit binary search
+// tree. Indices are 1-based, the children of `k` being `2k` and `2k + 1`, so
+// the first slot is unused.
+#[derive(Debug, Clone)]
+pub struct EytzingerArray<E> {
+    elements: Vec<E>,
+    prefetch: bool,
+}
+
+impl<E> EytzingerArray<E>
+where
+    E: Element,
+{
+    pub fn from_sorted(sorted: &[E], prefetch: bool) -> Self {
+        let mut elements = vec![E::MAX; sorted.len() + 1];
+        let mut index = first_in_order(sorted.len());
+        for &element in sorted {
+            elements[index] = element;
+            trace::touch(&elements[index]);
+            index = next_in_order(index, sorted.len());
+        }
+        Self { elements, prefetch }
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len() - 1
+    }
+
+    // Index of the first element not less than `element`, or 0 if there is
+    // none.
+    fn lower_bound(&self, element: E) -> usize {
+        // Descendants `log2(elems_in_line)` levels down share a cache line.
+        let elems_in_line = (reuse_distance::CACHE_LINE_SIZE
+            / mem::size_of::<E>())
+        .max(2);
+        let mut index = 1;
+        while index <= self.len() {
+            if self.prefetch {
+                prefetch(self.elements.as_ptr().wrapping_add(
+                    index.wrapping_mul(elems_in_line),
+                ));
+            }
+            trace::touch(&self.elements[index]);
+            index = 2 * index + (self.elements[index] < element) as usize;
+        }
+        index >> (index.trailing_ones() + 1)
+    }
+
+    pub fn find(&self, element: E) -> bool {
+        let index = self.lower_bound(element);
+        index != 0 && self.elements[index] == element
+    }
+
+    // Elements less than `element` come first in order.
+    pub fn inc_less_than(&mut self, element: E) {
+        let mut index = first_in_order(self.len());
+        while index != 0 {
+            trace::touch(&self.elements[index]);
+            if self.elements[index] >= element {
+                break;
+            }
+            self.elements[index] = self.elements[index].wrapping_inc();
+            index = next_in_order(index, self.len());
+        }
+    }
+
+    pub fn storage(&self, pages: &mut PageSet) {
+        pages.insert_region(
+            self.elements.as_ptr() as usize,
+            self.allocated_bytes(),
+        );
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        self.elements.capacity() * mem::size_of::<E>()
+    }
+}
+
+// The next two walk the indices of a complete tree with `len` nodes in
+// order, ending with 0.
+pub fn first_in_order(len: usize) -> usize {
+    if len == 0 {
+        0
+    } else {
+        leftmost(1, len)
+    }
+}
+
+pub fn next_in_order(index: usize, len: usize) -> usize {
+    if 2 * index < len {
+        leftmost(2 * index + 1, len)
+    } else {
+        // Up past every right child, then once more.
+        index >> (index.trailing_ones() + 1)
+    }
+}
+
+fn leftmost(mut index: usize, len: usize) -> usize {
+    while 2 * index <= len {
+        index *= 2;
+    }
+    index
+}
+
+// Prefetching never faults, even past the end of the array.
+#[cfg(target_arch = "x86_64")]
+fn prefetch<T>(pointer: *const T) {
+    use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+
+    unsafe { _mm_prefetch::<_MM_HINT_T0>(pointer as *const i8) }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn prefetch<T>(_pointer: *const T) {}
+
+impl<'array, E> IntoIterator for &'array EytzingerArray<E>
+where
+    E: Element,
+{
+    type Item = E;
+    type IntoIter = Iter<'array, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter { array: self, index: first_in_order(self.len()) }
+    }
+}
+
+#[derive(Debug)]
+pub struct Iter<'array, E> {
+    array: &'array EytzingerArray<E>,
+    index: usize,
+}
+
+impl<'array, E> Iterator for Iter<'array, E>
+where
+    E: Element,
+{
+    type Item = E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index == 0 {
+            return None;
+        }
+        let element = self.array.elements[self.index];
+        self.index = next_in_order(self.index, self.array.len());
+        Some(element)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EytzingerArray;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn same_as_sorted() {
+        let mut rng = StdRng::seed_from_u64(11);
+        for len in [0, 1, 2, 7, 8, 1000] {
+            let mut sorted: Vec<u64> =
+                (0 .. len).map(|_| rng.gen_range(0 .. 300)).collect();
+            sorted.sort();
+            let mut array = EytzingerArray::from_sorted(&sorted, len > 7);
+            assert!(array.into_iter().eq(sorted.iter().copied()));
+
+            for _ in 0 .. 20 {
+                let target = rng.gen_range(0 .. 350);
+                array.inc_less_than(target);
+                for element in &mut sorted {
+                    *element += (*element < target) as u64;
+                }
+                assert!(array.into_iter().eq(sorted.iter().copied()));
+                for element in 0 .. 350 {
+                    assert_eq!(
+                        array.find(element),
+                        sorted.binary_search(&element).is_ok()
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{element::Element, residency::PageSet, trace};
+use std::mem;
+
+// Fenwick tree over a fixed number of entries, all starting at 0.
+#[derive(Debug, Clone)]
+pub struct FenwickTree {
+    // 1-based, each node summing the entries of its range.
+    nodes: Vec<i64>,
+    // Whether accesses to the nodes are traced, which the reuse profiler
+    // does not do for its own tree.
+    traced: bool,
+}
+
+impl FenwickTree {
+    pub fn new(len: usize) -> Self {
+        Self { nodes: vec![0; len + 1], traced: false }
+    }
+
+    pub fn traced(len: usize) -> Self {
+        Self { nodes: vec![0; len + 1], traced: true }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    fn node(&self, node: usize) -> i64 {
+        if self.traced {
+            trace::touch(&self.nodes[node]);
+        }
+        self.nodes[node]
+    }
+
+    pub fn add(&mut self, index: usize, amount: i64) {
+        let mut node = index + 1;
+        while node < self.nodes.len() {
+            if self.traced {
+                trace::touch(&self.nodes[node]);
+            }
+            self.nodes[node] += amount;
+            node += node & node.wrapping_neg();
+        }
+    }
+
+    // Sum of the entries before `end`.
+    pub fn prefix_sum(&self, end: usize) -> i64 {
+        let mut sum = 0;
+        let mut node = end;
+        while node > 0 {
+            sum += self.node(node);
+            node &= node - 1;
+        }
+        sum
+    }
+
+    // Number of entries for which `pred`, given the index and the sum of
+    // the entries up to it, holds, when it only holds for a prefix. Descends
+    // the tree like a binary search, carrying the sum of the prefix passed,
+    // so that `pred` is asked in logarithmic time.
+    pub fn partition_point<F>(&self, mut pred: F) -> usize
+    where
+        F: FnMut(usize, i64) -> bool,
+    {
+        let mut count = 0;
+        let mut sum = 0;
+        let mut step = (self.len() + 1).next_power_of_two() / 2;
+        while step > 0 {
+            let node = count + step;
+            if node <= self.len() {
+                let node_sum = sum + self.node(node);
+                if pred(node - 1, node_sum) {
+                    count = node;
+                    sum = node_sum;
+                }
+            }
+            step /= 2;
+        }
+        count
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        self.nodes.capacity() * mem::size_of::<i64>()
+    }
+
+    fn storage(&self, pages: &mut PageSet) {
+        pages.insert_region(
+            self.nodes.as_ptr() as usize,
+            self.allocated_bytes(),
+        );
+    }
+}
+
+// Sorted array whose increments are kept apart, in a Fenwick tree, so that
+// incrementing the elements less than some element only updates two
+// counters. Elements are read as their base value plus their increments.
+// Incrementing keeps the order, duplicates being allowed as in a sorted
+// array, so that neighbours made equal still compare the same.
+#[derive(Debug, Clone)]
+pub struct FenwickArray<E> {
+    // Sorted elements before any increment.
+    base: Vec<E>,
+    // Differences between the increments of each element and the one
+    // before it.
+    tree: FenwickTree,
+}
+
+impl<E> FenwickArray<E>
+where
+    E: Element,
+{
+    pub fn from_sorted(sorted: &[E]) -> Self {
+        Self { base: sorted.to_vec(), tree: FenwickTree::traced(sorted.len()) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.base.len()
+    }
+
+    // Sum of the differences up to the index.
+    fn increments(&self, index: usize) -> u64 {
+        self.tree.prefix_sum(index + 1) as u64
+    }
+
+    pub fn get(&self, index: usize) -> E {
+        trace::touch(&self.base[index]);
+        self.base[index].wrapping_inc_by(self.increments(index))
+    }
+
+    // Index of the first element not less than `element`, each compared
+    // with the increments the tree gives while it is searched.
+    fn lower_bound(&self, element: E) -> usize {
+        self.tree.partition_point(|index, increments| {
+            trace::touch(&self.base[index]);
+            self.base[index].wrapping_inc_by(increments as u64) < element
+        })
+    }
+
+    pub fn find(&self, element: E) -> bool {
+        let index = self.lower_bound(element);
+        index < self.len() && self.get(index) == element
+    }
+
+    // Elements less than `element` are a prefix.
+    pub fn inc_less_than(&mut self, element: E) {
+        let count = self.lower_bound(element);
+        if count == 0 {
+            return;
+        }
+        self.tree.add(0, 1);
+        if count < self.len() {
+            self.tree.add(count, -1);
+        }
+    }
+
+    pub fn storage(&self, pages: &mut PageSet) {
+        pages.insert_region(
+            self.base.as_ptr() as usize,
+            self.base.capacity() * mem::size_of::<E>(),
+        );
+        self.tree.storage(pages);
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        self.base.capacity() * mem::size_of::<E>()
+            + self.tree.allocated_bytes()
+    }
+}
+
+impl<'array, E> IntoIterator for &'array FenwickArray<E>
+where
+    E: Element,
+{
+    type Item = E;
+    type IntoIter = Iter<'array, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter { array: self, index: 0 }
+    }
+}
+
+#[derive(Debug)]
+pub struct Iter<'array, E> {
+    array: &'array FenwickArray<E>,
+    index: usize,
+}
+
+impl<'array, E> Iterator for Iter<'array, E>
+where
+    E: Element,
+{
+    type Item = E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index == self.array.len() {
+            return None;
+        }
+        let element = self.array.get(self.index);
+        self.index += 1;
+        Some(element)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FenwickArray;
+    use crate::array::Array;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn same_as_sorted_array() {
+        let mut rng = StdRng::seed_from_u64(29);
+        for len in [0, 1, 2, 7, 8, 1000] {
+            let mut array = Array::<u64>::empty();
+            for _ in 0 .. len {
+                array.append(rng.gen_range(0 .. 300)).unwrap();
+            }
+            array.sort();
+            let sorted: Vec<_> = array.into_iter().collect();
+            let mut fenwick = FenwickArray::from_sorted(&sorted);
+
+            // Narrow targets make neighbours collide.
+            for _ in 0 .. 50 {
+                let target = rng.gen_range(0 .. 350);
+                fenwick.inc_less_than(target);
+                array.inc_less_than_sorted(target);
+                assert!(fenwick.into_iter().eq(array.into_iter()));
+                for element in 0 .. 350 {
+                    assert_eq!(
+                        fenwick.find(element),
+                        array.find_sorted(element)
+                    );
+                }
+            }
+        }
+    }
+}
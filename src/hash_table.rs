@@ -0,0 +1,492 @@
+use crate::{
+    element::Element,
+    pool::{NodeBox, Pool},
+    residency::PageSet,
+    trace,
+};
+use std::{alloc::Layout, mem, slice};
+
+// Capacities are powers of two, at least this.
+const MIN_CAPACITY: usize = 8;
+
+// Fibonacci hashing: the high bits of the product depend on every bit of the
+// element.
+fn bucket<E>(element: E, capacity: usize) -> usize
+where
+    E: Element,
+{
+    let hash = element.hash_bits().wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    (hash >> (u64::BITS - capacity.trailing_zeros())) as usize
+}
+
+fn exceeds_load(len: usize, capacity: usize, load_factor: f64) -> bool {
+    len as f64 > capacity as f64 * load_factor
+}
+
+// Open addressing with linear probing in one array, keeping duplicates.
+// Empty slots hold `E::MAX`, so copies of it are only counted.
+#[derive(Debug, Clone)]
+pub struct OpenTable<E> {
+    slots: Vec<E>,
+    len: usize,
+    max_count: usize,
+    load_factor: f64,
+    // Elements taken out by inc-less-than, kept to reuse its allocation.
+    scratch: Vec<E>,
+}
+
+impl<E> OpenTable<E>
+where
+    E: Element,
+{
+    // The load factor must be less than 1, leaving empty slots to end
+    // probing.
+    pub fn new(load_factor: f64) -> Self {
+        Self {
+            slots: vec![E::MAX; MIN_CAPACITY],
+            len: 0,
+            max_count: 0,
+            load_factor,
+            scratch: Vec::new(),
+        }
+    }
+
+    pub fn insert(&mut self, element: E) {
+        if element == E::MAX {
+            self.max_count += 1;
+            return;
+        }
+        if exceeds_load(self.len + 1, self.slots.len(), self.load_factor) {
+            self.grow();
+        }
+        self.place(element);
+        self.len += 1;
+    }
+
+    fn place(&mut self, element: E) {
+        let mask = self.slots.len() - 1;
+        let mut index = bucket(element, self.slots.len());
+        loop {
+            trace::touch(&self.slots[index]);
+            if self.slots[index] == E::MAX {
+                self.slots[index] = element;
+                return;
+            }
+            index = (index + 1) & mask;
+        }
+    }
+
+    fn grow(&mut self) {
+        let capacity = self.slots.len() * 2;
+        let slots = mem::replace(&mut self.slots, vec![E::MAX; capacity]);
+        for element in slots {
+            trace::touch(&element);
+            if element != E::MAX {
+                self.place(element);
+            }
+        }
+    }
+
+    pub fn find(&self, element: E) -> bool {
+        if element == E::MAX {
+            return self.max_count > 0;
+        }
+        let mask = self.slots.len() - 1;
+        let mut index = bucket(element, self.slots.len());
+        loop {
+            trace::touch(&self.slots[index]);
+            if self.slots[index] == element {
+                return true;
+            }
+            if self.slots[index] == E::MAX {
+                return false;
+            }
+            index = (index + 1) & mask;
+        }
+    }
+
+    // Empties the slot, then shifts back the elements after it which would
+    // no longer be found, so probing never needs tombstones.
+    fn remove_at(&mut self, mut hole: usize) {
+        let mask = self.slots.len() - 1;
+        self.slots[hole] = E::MAX;
+        self.len -= 1;
+        let mut index = hole;
+        loop {
+            index = (index + 1) & mask;
+            trace::touch(&self.slots[index]);
+            let element = self.slots[index];
+            if element == E::MAX {
+                break;
+            }
+            // Moves it if its bucket is not between the hole and it.
+            let home = bucket(element, self.slots.len());
+            if index.wrapping_sub(home) & mask
+                >= index.wrapping_sub(hole) & mask
+            {
+                self.slots[hole] = element;
+                self.slots[index] = E::MAX;
+                hole = index;
+            }
+        }
+    }
+
+    // Incremented elements hash elsewhere, so they are taken out and
+    // inserted again. Removing only moves elements back into the slot being
+    // visited, or into visited slots after wrapping around.
+    pub fn inc_less_than(&mut self, element: E) {
+        let mut incremented = mem::take(&mut self.scratch);
+        let mut index = 0;
+        while index < self.slots.len() {
+            trace::touch(&self.slots[index]);
+            let current = self.slots[index];
+            if current != E::MAX && current < element {
+                incremented.push(current.wrapping_inc());
+                self.remove_at(index);
+            } else {
+                index += 1;
+            }
+        }
+        for element in incremented.drain(..) {
+            self.insert(element);
+        }
+        self.scratch = incremented;
+    }
+
+    pub fn storage(&self, pages: &mut PageSet) {
+        pages.insert_region(
+            self.slots.as_ptr() as usize,
+            self.slots.capacity() * mem::size_of::<E>(),
+        );
+        pages.insert_region(
+            self.scratch.as_ptr() as usize,
+            self.scratch.capacity() * mem::size_of::<E>(),
+        );
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        (self.slots.capacity() + self.scratch.capacity()) * mem::size_of::<E>()
+    }
+}
+
+impl<'table, E> IntoIterator for &'table OpenTable<E>
+where
+    E: Element,
+{
+    type Item = E;
+    type IntoIter = OpenIter<'table, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        OpenIter { table: self, index: 0, max_count: self.max_count }
+    }
+}
+
+// Elements in slot order, then the copies of `E::MAX`.
+#[derive(Debug)]
+pub struct OpenIter<'table, E> {
+    table: &'table OpenTable<E>,
+    index: usize,
+    max_count: usize,
+}
+
+impl<'table, E> Iterator for OpenIter<'table, E>
+where
+    E: Element,
+{
+    type Item = E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(&element) = self.table.slots.get(self.index) {
+            self.index += 1;
+            if element != E::MAX {
+                return Some(element);
+            }
+        }
+        if self.max_count == 0 {
+            return None;
+        }
+        self.max_count -= 1;
+        Some(E::MAX)
+    }
+}
+
+type Link<E> = Option<NodeBox<Node<E>>>;
+
+#[derive(Debug)]
+struct Node<E> {
+    data: E,
+    next: Link<E>,
+}
+
+// Separate chaining, with a list of boxed nodes per bucket, keeping
+// duplicates.
+#[derive(Debug)]
+pub struct ChainedTable<E> {
+    buckets: Vec<Link<E>>,
+    len: usize,
+    load_factor: f64,
+}
+
+impl<E> ChainedTable<E>
+where
+    E: Element,
+{
+    pub fn new(load_factor: f64) -> Self {
+        Self {
+            buckets: (0 .. MIN_CAPACITY).map(|_| None).collect(),
+            len: 0,
+            load_factor,
+        }
+    }
+
+    pub fn node_layout() -> Layout {
+        Layout::new::<Node<E>>()
+    }
+
+    pub fn insert(&mut self, element: E) {
+        self.insert_with(element, NodeBox::new);
+    }
+
+    // The pool must outlive the table.
+    pub unsafe fn insert_in(&mut self, element: E, pool: &mut Pool) {
+        self.insert_with(element, |node| pool.alloc(node));
+    }
+
+    fn insert_with<F>(&mut self, element: E, alloc: F)
+    where
+        F: FnOnce(Node<E>) -> NodeBox<Node<E>>,
+    {
+        if exceeds_load(self.len + 1, self.buckets.len(), self.load_factor) {
+            self.grow();
+        }
+        self.link(alloc(Node { data: element, next: None }));
+        self.len += 1;
+    }
+
+    fn link(&mut self, mut node: NodeBox<Node<E>>) {
+        let index = bucket(node.data, self.buckets.len());
+        trace::touch(&self.buckets[index]);
+        node.next = self.buckets[index].take();
+        let node = self.buckets[index].insert(node);
+        trace::touch(&**node);
+    }
+
+    // Moves the nodes themselves to the new buckets.
+    fn grow(&mut self) {
+        let capacity = self.buckets.len() * 2;
+        let buckets = mem::replace(
+            &mut self.buckets,
+            (0 .. capacity).map(|_| None).collect(),
+        );
+        for mut link in buckets {
+            while let Some(mut node) = link {
+                trace::touch(&*node);
+                link = node.next.take();
+                self.link(node);
+            }
+        }
+    }
+
+    pub fn find(&self, element: E) -> bool {
+        let index = bucket(element, self.buckets.len());
+        trace::touch(&self.buckets[index]);
+        let mut link = &self.buckets[index];
+        while let Some(node) = link {
+            trace::touch(&**node);
+            if node.data == element {
+                return true;
+            }
+            link = &node.next;
+        }
+        false
+    }
+
+    // Incremented elements hash elsewhere, so their nodes are unlinked
+    // into a list and linked again.
+    pub fn inc_less_than(&mut self, element: E) {
+        let mut incremented: Link<E> = None;
+        for bucket in &mut self.buckets {
+            trace::touch(&*bucket);
+            let mut link = bucket;
+            while let Some(node) = link {
+                trace::touch(&**node);
+                if node.data < element {
+                    let mut node = link.take().expect("link is not empty");
+                    *link = node.next.take();
+                    node.data = node.data.wrapping_inc();
+                    node.next = incremented;
+                    incremented = Some(node);
+                } else {
+                    link = &mut link.as_mut().expect("link is not empty").next;
+                }
+            }
+        }
+        while let Some(mut node) = incremented {
+            incremented = node.next.take();
+            self.link(node);
+        }
+    }
+
+    pub fn storage(&self, pages: &mut PageSet) {
+        pages.insert_region(
+            self.buckets.as_ptr() as usize,
+            self.buckets.capacity() * mem::size_of::<Link<E>>(),
+        );
+        for bucket in &self.buckets {
+            let mut link = bucket;
+            while let Some(node) = link {
+                pages.insert(&**node);
+                link = &node.next;
+            }
+        }
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        let mut bytes = self.buckets.capacity() * mem::size_of::<Link<E>>();
+        for bucket in &self.buckets {
+            let mut link = bucket;
+            while let Some(node) = link {
+                bytes += node.allocated_size();
+                link = &node.next;
+            }
+        }
+        bytes
+    }
+}
+
+impl<E> Clone for ChainedTable<E>
+where
+    E: Element,
+{
+    fn clone(&self) -> Self {
+        let buckets = self
+            .buckets
+            .iter()
+            .map(|bucket| {
+                let mut new_bucket = None;
+                let mut new_end = &mut new_bucket;
+                let mut link = bucket;
+                while let Some(node) = link {
+                    let new_node = new_end.insert(NodeBox::new(Node {
+                        data: node.data,
+                        next: None,
+                    }));
+                    new_end = &mut new_node.next;
+                    link = &node.next;
+                }
+                new_bucket
+            })
+            .collect();
+        Self { buckets, len: self.len, load_factor: self.load_factor }
+    }
+}
+
+impl<'table, E> IntoIterator for &'table ChainedTable<E>
+where
+    E: Element,
+{
+    type Item = E;
+    type IntoIter = ChainedIter<'table, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        ChainedIter { buckets: self.buckets.iter(), link: &None }
+    }
+}
+
+// Elements bucket by bucket.
+#[derive(Debug)]
+pub struct ChainedIter<'table, E> {
+    buckets: slice::Iter<'table, Link<E>>,
+    link: &'table Link<E>,
+}
+
+impl<'table, E> Iterator for ChainedIter<'table, E>
+where
+    E: Element,
+{
+    type Item = E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(node) = self.link {
+                self.link = &node.next;
+                return Some(node.data);
+            }
+            self.link = self.buckets.next()?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ChainedTable, OpenTable};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn sorted<I>(elements: I) -> Vec<u64>
+    where
+        I: IntoIterator<Item = u64>,
+    {
+        let mut elements: Vec<_> = elements.into_iter().collect();
+        elements.sort();
+        elements
+    }
+
+    #[test]
+    fn open_same_as_sorted() {
+        let mut rng = StdRng::seed_from_u64(17);
+        for load_factor in [0.25, 0.5, 0.9] {
+            let mut table = OpenTable::new(load_factor);
+            let mut expected = Vec::new();
+            for _ in 0 .. 2000 {
+                let element = rng.gen_range(0 .. 1000);
+                table.insert(element);
+                expected.push(element);
+            }
+            table.insert(u64::MAX);
+            expected.push(u64::MAX);
+            for _ in 0 .. 20 {
+                let target = rng.gen_range(0 .. 1100);
+                table.inc_less_than(target);
+                for element in &mut expected {
+                    *element += (*element < target) as u64;
+                }
+                assert_eq!(sorted(&table), sorted(expected.clone()));
+                for element in 0 .. 1100 {
+                    assert_eq!(
+                        table.find(element),
+                        expected.contains(&element)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn chained_same_as_sorted() {
+        let mut rng = StdRng::seed_from_u64(19);
+        for load_factor in [0.5, 1.0, 4.0] {
+            let mut table = ChainedTable::new(load_factor);
+            let mut expected = Vec::new();
+            for _ in 0 .. 2000 {
+                let element = rng.gen_range(0 .. 1000);
+                table.insert(element);
+                expected.push(element);
+            }
+            for _ in 0 .. 20 {
+                let target = rng.gen_range(0 .. 1100);
+                table.inc_less_than(target);
+                for element in &mut expected {
+                    *element += (*element < target) as u64;
+                }
+                assert_eq!(sorted(&table), sorted(expected.clone()));
+                assert_eq!(sorted(&table.clone()), sorted(expected.clone()));
+                for element in 0 .. 1100 {
+                    assert_eq!(
+                        table.find(element),
+                        expected.contains(&element)
+                    );
+                }
+            }
+        }
+    }
+}
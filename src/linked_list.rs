@@ -1,29 +1,59 @@
-use crate::Element;
+use crate::{
+    element::Element,
+    pool::{NodeBox, Pool},
+    residency::PageSet,
+    trace,
+};
+use std::alloc::Layout;
 
 #[derive(Debug)]
-pub struct LinkedList {
-    top: Option<Box<Node>>,
+pub struct LinkedList<E> {
+    top: Option<NodeBox<Node<E>>>,
 }
 
-impl Default for LinkedList {
+impl<E> Default for LinkedList<E>
+where
+    E: Element,
+{
     fn default() -> Self {
         Self::empty()
     }
 }
 
-impl LinkedList {
+impl<E> LinkedList<E>
+where
+    E: Element,
+{
     pub fn empty() -> Self {
         LinkedList { top: None }
     }
 
-    pub fn prepend(&mut self, element: Element) {
+    pub fn node_layout() -> Layout {
+        Layout::new::<Node<E>>()
+    }
+
+    pub fn prepend(&mut self, element: E) {
+        self.prepend_with(element, NodeBox::new);
+    }
+
+    // The pool must outlive the list.
+    pub unsafe fn prepend_in(&mut self, element: E, pool: &mut Pool) {
+        self.prepend_with(element, |node| pool.alloc(node));
+    }
+
+    fn prepend_with<F>(&mut self, element: E, alloc: F)
+    where
+        F: FnOnce(Node<E>) -> NodeBox<Node<E>>,
+    {
         let next = LinkedList { top: self.top.take() };
-        self.top = Some(Box::new(Node { data: element, next }));
+        let top = self.top.insert(alloc(Node { data: element, next }));
+        trace::touch(&**top);
     }
 
-    pub fn find(&self, element: Element) -> bool {
+    pub fn find(&self, element: E) -> bool {
         let mut this = self;
         while let Some(top) = &this.top {
+            trace::touch(&**top);
             if top.data == element {
                 return true;
             }
@@ -32,25 +62,47 @@ impl LinkedList {
         false
     }
 
-    pub fn inc_less_than(&mut self, element: Element) {
+    pub fn storage(&self, pages: &mut PageSet) {
+        let mut this = self;
+        while let Some(top) = &this.top {
+            pages.insert(&**top);
+            this = &top.next;
+        }
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        let mut this = self;
+        let mut bytes = 0;
+        while let Some(top) = &this.top {
+            bytes += top.allocated_size();
+            this = &top.next;
+        }
+        bytes
+    }
+
+    pub fn inc_less_than(&mut self, element: E) {
         let mut this = self;
         while let Some(top) = &mut this.top {
+            trace::touch(&**top);
             if top.data < element {
-                top.data = top.data.wrapping_add(1);
+                top.data = top.data.wrapping_inc();
             }
             this = &mut top.next;
         }
     }
 }
 
-impl Clone for LinkedList {
+impl<E> Clone for LinkedList<E>
+where
+    E: Element,
+{
     fn clone(&self) -> Self {
         let mut source_list = self;
         let mut new_list = LinkedList::empty();
         let mut new_list_end = &mut new_list.top;
 
         while let Some(node) = &source_list.top {
-            *new_list_end = Some(Box::new(Node {
+            *new_list_end = Some(NodeBox::new(Node {
                 data: node.data,
                 next: LinkedList::empty(),
             }));
@@ -62,29 +114,35 @@ impl Clone for LinkedList {
     }
 }
 
-impl Drop for LinkedList {
+impl<E> Drop for LinkedList<E> {
     fn drop(&mut self) {
-        while let Some(top) = self.top.take() {
-            *self = top.next;
+        while let Some(mut top) = self.top.take() {
+            self.top = top.next.top.take();
         }
     }
 }
 
-impl<'list> IntoIterator for &'list LinkedList {
-    type Item = Element;
-    type IntoIter = Iter<'list>;
+impl<'list, E> IntoIterator for &'list LinkedList<E>
+where
+    E: Element,
+{
+    type Item = E;
+    type IntoIter = Iter<'list, E>;
 
     fn into_iter(self) -> Self::IntoIter {
         Iter { list: self }
     }
 }
 
-pub struct Iter<'list> {
-    list: &'list LinkedList,
+pub struct Iter<'list, E> {
+    list: &'list LinkedList<E>,
 }
 
-impl<'list> Iterator for Iter<'list> {
-    type Item = Element;
+impl<'list, E> Iterator for Iter<'list, E>
+where
+    E: Element,
+{
+    type Item = E;
 
     fn next(&mut self) -> Option<Self::Item> {
         match &self.list.top {
@@ -99,9 +157,9 @@ impl<'list> Iterator for Iter<'list> {
 }
 
 #[derive(Debug)]
-struct Node {
-    data: Element,
-    next: LinkedList,
+struct Node<E> {
+    data: E,
+    next: LinkedList<E>,
 }
 
 #[cfg(test)]
@@ -110,7 +168,7 @@ mod test {
 
     #[test]
     fn iterate() {
-        let mut list = LinkedList::empty();
+        let mut list = LinkedList::<u64>::empty();
         list.prepend(10);
         list.prepend(3);
         list.prepend(5);
@@ -127,7 +185,7 @@ mod test {
 
     #[test]
     fn find() {
-        let mut list = LinkedList::empty();
+        let mut list = LinkedList::<u64>::empty();
         list.prepend(10);
         list.prepend(3);
         list.prepend(5);
@@ -142,7 +200,7 @@ mod test {
 
     #[test]
     fn inc_less_than() {
-        let mut list = LinkedList::empty();
+        let mut list = LinkedList::<u64>::empty();
         list.prepend(10);
         list.prepend(3);
         list.prepend(5);
@@ -1,36 +1,68 @@
+mod allocator;
+mod arena_tree;
 mod array;
+mod balanced_tree;
+mod btree;
+mod cache;
+mod clock;
+mod collection;
+mod config;
+mod doubly_linked_list;
+mod element;
+mod environment;
+mod eytzinger;
+mod fenwick;
+mod hash_table;
 mod linked_list;
+mod memory_limit;
+mod mmap;
+mod pool;
+mod record;
+mod registry;
+mod residency;
+mod reuse_distance;
+mod self_organizing;
+mod skip_list;
+mod trace;
 mod tree;
-mod collection;
+mod unrolled_list;
+mod veb;
+mod xor_linked_list;
 
+use arena_tree::TreeLayout;
+use cache::CacheState;
 use clap::Parser;
-use collection::Collection;
+use clock::{Clock, ClockSource};
+use config::{ArrayBacking, Config};
+use element::{Distribution, Element, ElementType};
+use memory_limit::{Ballast, Cgroup};
+use mmap::{Advice, HugePages, MapOptions};
+use pool::Placement;
 use rand::{rngs::StdRng, Rng, SeedableRng};
+use record::Recorder;
+use registry::{registry, NewRunner, Workload};
+use reuse_distance::CACHE_LINE_SIZE;
 use std::{
+    env,
     error::Error,
     fmt,
     fs,
     io,
     mem,
-    path::PathBuf,
-    process,
+    path::{Path, PathBuf},
+    os::unix::process::ExitStatusExt,
+    process::{self, Child, Command, ExitStatus, Stdio},
     str::FromStr,
+    thread,
+    time::{Duration, Instant},
 };
 
-type Element = u64;
-
-const ELEMS_IN_PAGE: usize = 0x1000 / mem::size_of::<Element>();
-const SIZES: [usize; 9] = [
-    ELEMS_IN_PAGE / 4usize.pow(3),
-    ELEMS_IN_PAGE / 4usize.pow(2),
-    ELEMS_IN_PAGE / 4usize.pow(1),
-    ELEMS_IN_PAGE * 4usize.pow(0),
-    ELEMS_IN_PAGE * 4usize.pow(1),
-    ELEMS_IN_PAGE * 4usize.pow(2),
-    ELEMS_IN_PAGE * 4usize.pow(3),
-    ELEMS_IN_PAGE * 4usize.pow(4),
-    ELEMS_IN_PAGE * 4usize.pow(5),
-];
+const PAGE_SIZE: usize = 0x1000;
+const BTREE_NODE_SIZE: usize = 256;
+const OPEN_LOAD_FACTOR: f64 = 0.5;
+const CHAINED_LOAD_FACTOR: f64 = 1.0;
+const OPERATIONS: [&str; 6] =
+    ["create", "inc-less-than", "find", "skewed-find", "clone", "fork-cow"];
 
 #[derive(Debug, Clone)]
 struct SeedError;
@@ -43,6 +75,24 @@ impl fmt::Display for SeedError {
 
 impl Error for SeedError {}
 
+// The same sizes in bytes for every element type.
+fn sizes<E>() -> [usize; 9]
+where
+    E: Element,
+{
+    [
+        E::ELEMS_IN_PAGE / 4usize.pow(3),
+        E::ELEMS_IN_PAGE / 4usize.pow(2),
+        E::ELEMS_IN_PAGE / 4usize.pow(1),
+        E::ELEMS_IN_PAGE * 4usize.pow(0),
+        E::ELEMS_IN_PAGE * 4usize.pow(1),
+        E::ELEMS_IN_PAGE * 4usize.pow(2),
+        E::ELEMS_IN_PAGE * 4usize.pow(3),
+        E::ELEMS_IN_PAGE * 4usize.pow(4),
+        E::ELEMS_IN_PAGE * 4usize.pow(5),
+    ]
+}
+
 #[derive(Debug, Clone, Copy)]
 struct Seed {
     bytes: [u8; 32],
@@ -51,6 +101,7 @@ struct Seed {
 impl FromStr for Seed {
     type Err = SeedError;
 
+    #[allow(clippy::explicit_counter_loop)]
     fn from_str(input: &str) -> Result<Self, Self::Err> {
         let mut this = Self { bytes: [0; 32] };
         let mut current = 0;
@@ -84,17 +135,266 @@ struct Arguments {
     seed: Seed,
     #[clap(short, long)]
     truncate: bool,
+    /// Type of the elements: u32, u64, u128, or record, a 64-byte record
+    /// compared by a u64 key.
+    #[clap(long, default_value = "u64")]
+    element: ElementType,
+    /// Order of the input: random, sorted, or nearly-sorted, sorted with
+    /// one in a hundred elements swapped with another.
+    #[clap(long, default_value = "random")]
+    distribution: Distribution,
+    /// Also write LRU stack distance histograms of every operation to this
+    /// file. Requires the trace-reuse feature. Tracing slows operations
+    /// down, so recorded times of such a run should not be compared against
+    /// untraced runs.
+    #[clap(long)]
+    reuse_distance: Option<PathBuf>,
+    /// Also write how many pages backing each collection are resident in
+    /// memory after every operation to this file.
+    #[clap(long)]
+    residency: Option<PathBuf>,
+    /// Also write minor and major page faults of every operation to this
+    /// file.
+    #[clap(long)]
+    faults: Option<PathBuf>,
+    /// Also run the fork-cow operation, which forks after the other
+    /// operations and runs inc-less-than in the child, writing its time,
+    /// faults and the parent's RSS growth to this file.
+    #[clap(long)]
+    fork_cow: Option<PathBuf>,
+    /// Make the fork-cow child write to every element instead of running
+    /// inc-less-than with the usual targets.
+    #[clap(long, requires = "fork-cow")]
+    fork_cow_full: bool,
+    /// Skip input sizes larger than this number of bytes.
+    #[clap(long)]
+    max_size: Option<usize>,
+    /// Only run the input size with this number of bytes.
+    #[clap(long)]
+    only_size: Option<usize>,
+    /// Run each (size, collection, operation) cell in a child process
+    /// limited to this number of bytes, through a cgroup v2 `memory.max`
+    /// when possible, or else by locking the remaining available memory
+    /// away.
+    #[clap(long)]
+    memory_limit: Option<usize>,
+    /// Run each (size, collection, operation) cell in its own child process,
+    /// recording crashed, killed and timed out cells as failed rows.
+    #[clap(long)]
+    isolate: bool,
+    /// Kill child processes of isolated or memory limited runs after this
+    /// number of seconds.
+    #[clap(long)]
+    timeout: Option<u64>,
+    /// Only run this collection, and only record the operation given by
+    /// `--only-operation`.
+    #[clap(long, requires_all = &["only-size", "only-operation"])]
+    only_collection: Option<String>,
+    /// Operation recorded for the collection given by `--only-collection`,
+    /// after running the operations before it unrecorded.
+    #[clap(long, requires = "only-collection")]
+    only_operation: Option<String>,
+    /// Allocate arrays with `mmap` instead of the heap.
+    #[clap(long)]
+    mmap: bool,
+    /// Back `mmap`'d arrays with a file in this directory instead of
+    /// anonymous memory.
+    #[clap(long, requires = "mmap")]
+    mmap_dir: Option<PathBuf>,
+    /// Apply this `madvise` hint to `mmap`'d arrays: sequential, random,
+    /// will-need, huge-page or no-huge-page.
+    #[clap(long, requires = "mmap")]
+    madvise: Option<Advice>,
+    /// Map arrays with `MAP_POPULATE`.
+    #[clap(long, requires = "mmap")]
+    map_populate: bool,
+    /// Allocate linked list nodes from a pool, placing them in sequential,
+    /// reversed or shuffled slots, or one per page with page-strided.
+    #[clap(long)]
+    node_placement: Option<Placement>,
+    /// Order the nodes of arena trees after creating them: insertion, bfs
+    /// or dfs (pre-order).
+    #[clap(long, default_value = "insertion")]
+    tree_layout: TreeLayout,
+    /// Bytes of each B-tree node, keys and child pointers included, such as
+    /// 64 for a cache line or 4096 for a page.
+    #[clap(long, default_value_t = BTREE_NODE_SIZE)]
+    btree_node_size: usize,
+    /// Bytes of each unrolled linked list node, its header included, such
+    /// as 64 for a cache line or 4096 for a page.
+    #[clap(long, default_value_t = CACHE_LINE_SIZE)]
+    unrolled_node_size: usize,
+    /// Prefetch the cache line holding descendants a few levels down at
+    /// every step of Eytzinger array searches.
+    #[clap(long)]
+    prefetch: bool,
+    /// Elements per slot above which the open table grows, less than 1.
+    #[clap(long, default_value_t = OPEN_LOAD_FACTOR)]
+    open_load_factor: f64,
+    /// Elements per bucket above which the chained table grows, which may
+    /// be more than 1.
+    #[clap(long, default_value_t = CHAINED_LOAD_FACTOR)]
+    chained_load_factor: f64,
+    /// Also run the skewed-find operation, looking up this many elements
+    /// drawn from the input with a Zipf distribution.
+    #[clap(long, default_value_t = 0)]
+    skewed_finds: usize,
+    /// Page size in bytes assumed by the strides of bad and worse locality
+    /// arrays.
+    #[clap(long, default_value_t = PAGE_SIZE)]
+    page_size: usize,
+    /// Back arrays and list and tree nodes with huge pages, either thp
+    /// (`madvise(MADV_HUGEPAGE)`) or hugetlb (`MAP_HUGETLB`), when available.
+    #[clap(long, conflicts_with = "mmap-dir")]
+    huge_pages: Option<HugePages>,
+    /// Cgroup the process must join before running, set by the parent of a
+    /// memory limited run.
+    #[clap(long, hide = true)]
+    cgroup: Option<PathBuf>,
+    /// Also write the payload and overhead bytes, RSS growth and pages
+    /// spanned by each collection after create to this file.
+    #[clap(long)]
+    footprint: Option<PathBuf>,
+    /// Also write the height of each tree after create to this file.
+    #[clap(long)]
+    heights: Option<PathBuf>,
+    /// Also write allocation counts of every operation to this file, and run
+    /// the clone operation. Requires the count-allocations feature.
+    #[clap(long)]
+    allocations: Option<PathBuf>,
+    /// Also write the number of allocations per power of two size class of
+    /// every operation to this file, and run the clone operation. Requires
+    /// the count-allocations feature.
+    #[clap(long)]
+    allocation_sizes: Option<PathBuf>,
+    /// Clock timing operations: monotonic, monotonic-raw, thread-cputime,
+    /// process-cputime, or the time stamp counter read with fences, rdtsc or
+    /// rdtscp. Its measured overhead is subtracted from every time.
+    #[clap(long, default_value = "monotonic")]
+    clock: ClockSource,
+    /// Also write the wall and thread CPU time of every operation to this
+    /// file.
+    #[clap(long)]
+    times: Option<PathBuf>,
+    /// Evict caches (cold) or touch the collection (warm) before every
+    /// operation.
+    #[clap(long)]
+    cache_state: Option<CacheState>,
+    /// Also page out the collection with `madvise(MADV_PAGEOUT)` when
+    /// evicting caches.
+    #[clap(long, requires = "cache-state")]
+    page_out: bool,
+    /// Pin the benchmark to this CPU.
+    #[clap(long)]
+    cpu: Option<usize>,
+    /// Run under `SCHED_FIFO` with this priority (1 to 99).
+    #[clap(long)]
+    fifo: Option<i32>,
+    /// Run with this nice level.
+    #[clap(long, conflicts_with = "fifo")]
+    nice: Option<i32>,
+    /// Disable address space layout randomization by re-executing with the
+    /// `ADDR_NO_RANDOMIZE` personality.
+    #[clap(long)]
+    no_aslr: bool,
+    /// Write the CPU, scheduling, ASLR, frequency governor and THP settings
+    /// of the run, and detected noise sources, to this file.
+    #[clap(long)]
+    metadata: Option<PathBuf>,
+    /// Set by the parent of isolated or memory limited runs, which reads the
+    /// rows of the main output from the standard output.
+    #[clap(long, hide = true)]
+    child: bool,
 }
 
-#[derive(Debug, Clone)]
-struct Collections {
-    good_local_array: collection::GoodLocalArray,
-    bad_local_array: collection::BadLocalArray,
-    worse_local_array: collection::WorseLocalArray,
-    sorted_array: collection::SortedArray,
-    linked_list: collection::LinkedList,
-    with_order_tree: collection::WithOrderTree,
-    without_order_tree: collection::WithoutOrderTree,
+impl Arguments {
+    fn is_child(&self) -> bool {
+        self.child
+    }
+
+    // Collection, operation and size in bytes a child runs.
+    fn cell(&self) -> Option<(&str, &str, usize)> {
+        let collection_name = self.only_collection.as_deref()?;
+        let oper_name = self.only_operation.as_deref()?;
+        Some((collection_name, oper_name, self.only_size?))
+    }
+
+    fn operations(&self) -> impl Iterator<Item = &'static str> + '_ {
+        OPERATIONS.into_iter().filter(|&oper_name| match oper_name {
+            "clone" => self.records_allocations(),
+            "fork-cow" => self.fork_cow.is_some(),
+            "skewed-find" => self.skewed_finds > 0,
+            _ => true,
+        })
+    }
+
+    fn records_allocations(&self) -> bool {
+        self.allocations.is_some() || self.allocation_sizes.is_some()
+    }
+
+    fn output_paths(&self) -> impl Iterator<Item = &PathBuf> {
+        Some(&self.output)
+            .into_iter()
+            .chain(&self.reuse_distance)
+            .chain(&self.residency)
+            .chain(&self.faults)
+            .chain(&self.fork_cow)
+            .chain(&self.times)
+            .chain(&self.footprint)
+            .chain(&self.heights)
+            .chain(&self.allocations)
+            .chain(&self.allocation_sizes)
+    }
+
+    fn huge_pages(&self) -> Option<HugePages> {
+        let huge_pages = self.huge_pages?;
+        if huge_pages.is_available() {
+            Some(huge_pages)
+        } else {
+            eprintln!("Warning: {:?} huge pages are not available", huge_pages);
+            None
+        }
+    }
+
+    fn config(&self, huge_pages: Option<HugePages>) -> Config {
+        let array_backing = if self.mmap || huge_pages.is_some() {
+            ArrayBacking::Mapped(MapOptions {
+                file_dir: self.mmap_dir.clone(),
+                advice: self.madvise,
+                populate: self.map_populate,
+                huge_pages,
+            })
+        } else {
+            ArrayBacking::Heap
+        };
+        let node_pool = huge_pages.map(|huge_pages| MapOptions {
+            huge_pages: Some(huge_pages),
+            ..MapOptions::default()
+        });
+        Config {
+            array_backing,
+            page_size: self.page_size,
+            node_pool,
+            node_placement: self.node_placement,
+            tree_layout: self.tree_layout,
+            btree_node_size: self.btree_node_size,
+            unrolled_node_size: self.unrolled_node_size,
+            prefetch: self.prefetch,
+            open_load_factor: self.open_load_factor,
+            chained_load_factor: self.chained_load_factor,
+            skewed_finds: self.skewed_finds,
+            seed: self.seed.bytes,
+        }
+    }
+
+    fn selected<E>(&self, size: usize) -> bool
+    where
+        E: Element,
+    {
+        let bytes = size * mem::size_of::<E>();
+        self.max_size.is_none_or(|max_size| bytes <= max_size)
+            && self.only_size.is_none_or(|only_size| bytes == only_size)
+    }
 }
 
 fn main() {
@@ -106,245 +406,543 @@ fn main() {
 }
 
 fn try_main(arguments: &Arguments) -> io::Result<()> {
+    if arguments.no_aslr {
+        environment::disable_aslr()?;
+    }
+    if let Some(cpu) = arguments.cpu {
+        environment::pin_to_cpu(cpu)?;
+    }
+    if let Some(priority) = arguments.fifo {
+        environment::use_fifo(priority)?;
+    }
+    if let Some(nice) = arguments.nice {
+        environment::set_nice(nice)?;
+    }
+    let clock = Clock::new(arguments.clock)?;
+    if !arguments.is_child() {
+        check_environment(arguments, &clock)?;
+    }
+
+    match arguments.element {
+        ElementType::U32 => run::<u32>(arguments, clock),
+        ElementType::U64 => run::<u64>(arguments, clock),
+        ElementType::U128 => run::<u128>(arguments, clock),
+        ElementType::Record => run::<element::Record>(arguments, clock),
+    }
+}
+
+fn run<E>(arguments: &Arguments, clock: Clock) -> io::Result<()>
+where
+    E: Element,
+{
+    let element_size = mem::size_of::<E>();
+    if arguments.page_size == 0
+        || !arguments.page_size.is_multiple_of(element_size)
+    {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "page size must be a positive multiple of the element size",
+        ))?;
+    }
+    let open_load_factor = arguments.open_load_factor;
+    if !(open_load_factor > 0.0 && open_load_factor < 1.0) {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "open load factor must be greater than 0 and less than 1",
+        ))?;
+    }
+    let chained_load_factor = arguments.chained_load_factor;
+    if chained_load_factor.is_nan() || chained_load_factor <= 0.0 {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "chained load factor must be greater than 0",
+        ))?;
+    }
+    if arguments.records_allocations() && !allocator::is_installed() {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "counting allocations requires the count-allocations feature",
+        ))?;
+    }
+    if arguments.reuse_distance.is_some() && !trace::is_enabled() {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "reuse distance profiling requires the trace-reuse feature",
+        ))?;
+    }
+    let huge_pages = arguments.huge_pages();
+    let effective_page_size = huge_pages
+        .map_or_else(residency::system_page_size, HugePages::page_size);
+
+    if (arguments.memory_limit.is_some() || arguments.isolate)
+        && !arguments.is_child()
+    {
+        return run_children::<E>(arguments, effective_page_size);
+    }
+
+    let ballast = match (&arguments.cgroup, arguments.memory_limit) {
+        (Some(cgroup), _) => {
+            memory_limit::enter_cgroup(cgroup)?;
+            None
+        },
+        (None, Some(limit)) => Some(Ballast::reserve(limit)),
+        (None, None) => None,
+    };
+
+    // Children append to the files the parent has already truncated.
+    let truncate = arguments.truncate && !arguments.is_child();
+    let config = arguments.config(huge_pages);
+
     let mut rng = StdRng::from_seed(arguments.seed.bytes);
+    let csv_writer = if arguments.is_child() {
+        csv_writer(Box::new(io::stdout()))
+    } else {
+        open_csv(&arguments.output, truncate)?
+    };
+    let mut recorder = Recorder::new(&arguments.mode_name, csv_writer)
+        .page_size(effective_page_size)
+        .clock(clock);
+    if let Some(cache_state) = arguments.cache_state {
+        recorder = recorder.cache_state(cache_state, arguments.page_out);
+    }
+    if let Some(path) = &arguments.reuse_distance {
+        recorder = recorder.reuse_distance(open_csv(path, truncate)?);
+    }
+    if let Some(path) = &arguments.residency {
+        recorder = recorder.residency(open_csv(path, truncate)?);
+    }
+    if let Some(path) = &arguments.faults {
+        recorder = recorder.faults(open_csv(path, truncate)?);
+    }
+    if let Some(path) = &arguments.fork_cow {
+        recorder = recorder.fork_cow(open_csv(path, truncate)?);
+    }
+    if let Some(path) = &arguments.times {
+        recorder = recorder.times(open_csv(path, truncate)?);
+    }
+    if let Some(path) = &arguments.footprint {
+        recorder = recorder.footprint(open_csv(path, truncate)?);
+    }
+    if let Some(path) = &arguments.heights {
+        recorder = recorder.heights(open_csv(path, truncate)?);
+    }
+    if let Some(path) = &arguments.allocations {
+        recorder = recorder.allocations(open_csv(path, truncate)?);
+    }
+    if let Some(path) = &arguments.allocation_sizes {
+        recorder = recorder.allocation_sizes(open_csv(path, truncate)?);
+    }
+
+    // The cell of a child failing to lock its ballast gets a failed row
+    // telling why, rather than only its exit code.
+    let _ballast = match (ballast.transpose(), arguments.cell()) {
+        (Ok(ballast), _) => ballast,
+        (Err(error), Some((collection_name, oper_name, size))) => {
+            let failure = format!("ballast: {}", error);
+            recorder.record_failure(
+                collection_name,
+                oper_name,
+                size,
+                &failure,
+            )?;
+            return Ok(());
+        },
+        (Err(error), None) => Err(error)?,
+    };
+
+    // Inputs of skipped sizes are still drawn, so that every size gets the
+    // same elements no matter which sizes are run.
+    let drawn_sizes = sizes::<E>()
+        .iter()
+        .rposition(|&size| arguments.selected::<E>(size))
+        .map_or(0, |last_selected| last_selected + 1);
+    for size in sizes::<E>().into_iter().take(drawn_sizes) {
+        let (elements, extra_element) =
+            random_input::<E, _>(size, arguments.distribution, &mut rng);
+        if !arguments.selected::<E>(size) {
+            continue;
+        }
+        let target_elements = [
+            elements[elements.len() / 4],
+            elements[elements.len() / 2],
+            elements[3 * elements.len() / 4],
+            extra_element,
+        ];
+        let skewed_targets =
+            skewed_targets(&elements, &target_elements, &config);
+        let workload = Workload {
+            all_elements: &elements,
+            target_elements,
+            skewed_targets,
+            config: &config,
+            fork_targets: if arguments.fork_cow_full {
+                vec![E::MAX]
+            } else {
+                target_elements.to_vec()
+            },
+        };
+        let operations: Vec<_> = arguments.operations().collect();
+        match (&arguments.only_collection, &arguments.only_operation) {
+            (Some(collection_name), Some(oper_name)) => run_cell(
+                collection_name,
+                oper_name,
+                &operations,
+                &workload,
+                &mut recorder,
+            )?,
+            _ => {
+                run_for_size(
+                    &registry::<E, _>(),
+                    &operations,
+                    &operations,
+                    &workload,
+                    &mut recorder,
+                )?
+            },
+        }
+    }
+
+    Ok(())
+}
+
+fn check_environment(arguments: &Arguments, clock: &Clock) -> io::Result<()> {
+    let noise_sources = environment::noise_sources(arguments.cpu);
+    for noise_source in &noise_sources {
+        eprintln!("Warning: {}", noise_source);
+    }
+
+    let path = match &arguments.metadata {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let scheduling = match (arguments.fifo, arguments.nice) {
+        (Some(priority), _) => format!("fifo {}", priority),
+        (None, Some(nice)) => format!("nice {}", nice),
+        (None, None) => "default".to_owned(),
+    };
+    let aslr = if environment::is_aslr_disabled() {
+        "disabled"
+    } else {
+        "enabled"
+    };
+    let mut governors = environment::governors(arguments.cpu);
+    governors.sort();
+    governors.dedup();
+
+    let mut entries = vec![
+        ("cpu", arguments.cpu.map_or("any".to_owned(), |cpu| cpu.to_string())),
+        ("scheduling", scheduling),
+        ("aslr", aslr.to_owned()),
+        ("governor", governors.join(" ")),
+        ("thp", environment::thp_mode().unwrap_or_default()),
+        ("clock", clock.source().name().to_owned()),
+        ("timer_overhead", clock.overhead().to_string()),
+        ("element", arguments.element.name().to_owned()),
+        ("distribution", arguments.distribution.name().to_owned()),
+    ];
+    if clock.source().is_tsc() {
+        entries.push(("tsc_tick_nanos", clock.tick_nanos().to_string()));
+    }
+    entries.extend(noise_sources.into_iter().map(|noise| ("noise", noise)));
+
+    let mut writer = open_csv(path, arguments.truncate)?;
+    record::write_metadata(&mut writer, &arguments.mode_name, &entries)
+}
+
+// Spawns this binary once per cell of every selected size, and copies the
+// rows children write to their standard output.
+fn run_children<E>(arguments: &Arguments, page_size: usize) -> io::Result<()>
+where
+    E: Element,
+{
+    if arguments.truncate {
+        for path in arguments.output_paths() {
+            fs::File::create(path)?;
+        }
+    }
+    let csv_writer = open_csv(&arguments.output, false)?;
+    let mut recorder =
+        Recorder::new(&arguments.mode_name, csv_writer).page_size(page_size);
+    if let Some(cache_state) = arguments.cache_state {
+        recorder = recorder.cache_state(cache_state, arguments.page_out);
+    }
+
+    let cgroup = match arguments.memory_limit {
+        Some(limit) => match Cgroup::create(limit) {
+            Ok(cgroup) => Some(cgroup),
+            Err(error) => {
+                Ballast::check(limit).map_err(|ballast_error| {
+                    io::Error::new(
+                        ballast_error.kind(),
+                        format!(
+                            "cannot use a cgroup memory limit ({}) nor lock \
+                             a ballast region ({})",
+                            error, ballast_error
+                        ),
+                    )
+                })?;
+                eprintln!(
+                    "Warning: cannot use a cgroup memory limit ({}), locking \
+                     a ballast region instead",
+                    error
+                );
+                None
+            },
+        },
+        None => None,
+    };
+    let cgroup_path = cgroup.as_ref().map(Cgroup::path);
+
+    let mut result = Ok(());
+    'sizes: for size in sizes::<E>() {
+        if !arguments.selected::<E>(size) {
+            continue;
+        }
+        let bytes = size * mem::size_of::<E>();
+        for (collection_name, _) in registry::<E, Box<dyn io::Write>>() {
+            for oper_name in arguments.operations() {
+                let cell = (collection_name, oper_name);
+                let cgroup = cgroup_path.as_deref();
+                match run_child(arguments, bytes, cell, cgroup) {
+                    Ok((output, failure)) => {
+                        recorder.pass_through(&output)?;
+                        if let Some(failure) = failure {
+                            recorder.record_failure(
+                                collection_name,
+                                oper_name,
+                                bytes,
+                                &failure,
+                            )?;
+                        }
+                    },
+                    Err(error) => {
+                        result = Err(error);
+                        break 'sizes;
+                    },
+                }
+            }
+        }
+    }
+
+    if let Some(cgroup) = cgroup {
+        cgroup.remove()?;
+    }
+    result
+}
+
+// Returns the rows written by the child and why it failed, if it did.
+fn run_child(
+    arguments: &Arguments,
+    bytes: usize,
+    (collection_name, oper_name): (&str, &str),
+    cgroup: Option<&Path>,
+) -> io::Result<(Vec<u8>, Option<String>)> {
+    let mut command = Command::new(env::current_exe()?);
+    command
+        .args(env::args_os().skip(1))
+        .arg("--child")
+        .stdout(Stdio::piped());
+    if arguments.only_size.is_none() {
+        command.arg("--only-size").arg(bytes.to_string());
+    }
+    command
+        .arg("--only-collection")
+        .arg(collection_name)
+        .arg("--only-operation")
+        .arg(oper_name);
+    if let Some(cgroup) = cgroup {
+        command.arg("--cgroup").arg(cgroup);
+    }
+
+    let oom_kills_before = cgroup.map(memory_limit::oom_kills).transpose()?;
+    let mut child = command.spawn()?;
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let reader = thread::spawn(move || {
+        let mut output = Vec::new();
+        io::Read::read_to_end(&mut stdout, &mut output).map(|_| output)
+    });
+    let timeout = arguments.timeout.map(Duration::from_secs);
+    let status = wait_child(&mut child, timeout)?;
+    let output = reader.join().expect("reader thread panicked")?;
+    let oom_kills_after = cgroup.map(memory_limit::oom_kills).transpose()?;
+
+    let failure = match status {
+        None => Some("timeout".to_owned()),
+        Some(status) if status.success() => None,
+        Some(_) if oom_kills_after > oom_kills_before => {
+            Some("oom-kill".to_owned())
+        },
+        Some(status) => Some(match status.signal() {
+            Some(signal) => format!("signal {}", signal),
+            None => format!("exit code {}", status.code().unwrap_or(-1)),
+        }),
+    };
+    Ok((output, failure))
+}
+
+// Kills the child once the timeout elapses, in which case no status is
+// returned.
+fn wait_child(
+    child: &mut Child,
+    timeout: Option<Duration>,
+) -> io::Result<Option<ExitStatus>> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return child.wait().map(Some),
+    };
+    let then = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if then.elapsed() >= timeout {
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn open_csv(
+    path: &Path,
+    truncate: bool,
+) -> io::Result<csv::Writer<Box<dyn io::Write>>> {
     let file = fs::OpenOptions::new()
         .create(true)
         .read(false)
         .write(true)
-        .truncate(arguments.truncate)
-        .append(!arguments.truncate)
-        .open(&arguments.output)?;
-
-    let mut csv_writer =
-        csv::WriterBuilder::new().has_headers(false).from_writer(file);
+        .truncate(truncate)
+        .append(!truncate)
+        .open(path)?;
 
-    for size in SIZES {
-        run_for_size(size, &arguments.mode_name, &mut rng, &mut csv_writer)?;
-    }
+    Ok(csv_writer(Box::new(file)))
+}
 
-    Ok(())
+fn csv_writer(writer: Box<dyn io::Write>) -> csv::Writer<Box<dyn io::Write>> {
+    // Failed rows of isolated runs have an extra column.
+    csv::WriterBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_writer(writer)
 }
 
-fn run_for_size<R, W>(
+fn random_input<E, R>(
     size: usize,
-    mode_name: &str,
+    distribution: Distribution,
     mut rng: R,
-    csv_writer: &mut csv::Writer<W>,
-) -> io::Result<()>
+) -> (Vec<E>, E)
 where
+    E: Element,
     R: Rng,
-    W: io::Write,
 {
-    let mut elements: Vec<Element> = vec![0; size];
-    rng.fill(&mut elements[..]);
-
-    let mut collections = run_creation(&elements, mode_name, csv_writer)?;
-    let extra_element = rng.gen();
-    run_inc_less_than(
-        &mut collections,
-        &elements,
-        extra_element,
-        mode_name,
-        csv_writer,
-    )?;
-    run_find(&collections, &elements, extra_element, mode_name, csv_writer)?;
-
-    Ok(())
+    let mut elements = vec![E::MAX; size];
+    E::fill_random(&mut elements, &mut rng);
+    distribution.arrange(&mut elements, &mut rng);
+    let extra_element = E::random(&mut rng);
+    (elements, extra_element)
 }
 
-fn run_creation<W>(
-    elements: &[Element],
-    mode_name: &str,
-    csv_writer: &mut csv::Writer<W>,
-) -> io::Result<Collections>
+fn skewed_targets<E>(
+    all_elements: &[E],
+    inc_targets: &[E],
+    config: &Config,
+) -> Vec<E>
 where
-    W: io::Write,
+    E: Element,
 {
-    let oper_name = "create";
-
-    let collections = Collections {
-        good_local_array: collection::GoodLocalArray::record_create(
-            elements, mode_name, oper_name, csv_writer,
-        )?,
-        bad_local_array: collection::BadLocalArray::record_create(
-            elements, mode_name, oper_name, csv_writer,
-        )?,
-        worse_local_array: collection::WorseLocalArray::record_create(
-            elements, mode_name, oper_name, csv_writer,
-        )?,
-        sorted_array: collection::SortedArray::record_create(
-            elements, mode_name, oper_name, csv_writer,
-        )?,
-        linked_list: collection::LinkedList::record_create(
-            elements, mode_name, oper_name, csv_writer,
-        )?,
-        with_order_tree: collection::WithOrderTree::record_create(
-            elements, mode_name, oper_name, csv_writer,
-        )?,
-        without_order_tree: collection::WithoutOrderTree::record_create(
-            elements, mode_name, oper_name, csv_writer,
-        )?,
-    };
-
-    Ok(collections)
+    let mut cumulative = Vec::with_capacity(all_elements.len());
+    let mut total = 0.0;
+    for rank in 1 ..= all_elements.len() {
+        total += 1.0 / rank as f64;
+        cumulative.push(total);
+    }
+    let mut rng = StdRng::from_seed(config.seed_for("skewed-find"));
+    (0 .. config.skewed_finds)
+        .map(|_| {
+            let point = rng.gen::<f64>() * total;
+            let index = cumulative
+                .partition_point(|&weight| weight <= point)
+                .min(all_elements.len() - 1);
+            let mut element = all_elements[index];
+            for &target in inc_targets {
+                if element < target {
+                    element = element.wrapping_inc();
+                }
+            }
+            element
+        })
+        .collect()
 }
 
-fn run_find<W>(
-    collections: &Collections,
-    all_elements: &[Element],
-    extra_element: Element,
-    mode_name: &str,
-    csv_writer: &mut csv::Writer<W>,
+// Runs the operations in order on every collection, recording those asked
+// for. Only one collection is alive at a time, dropped once every operation
+// ran on it, so that it does not compete for memory with the others.
+fn run_for_size<E, W>(
+    entries: &[(&str, NewRunner<E, W>)],
+    operations: &[&str],
+    recorded: &[&str],
+    workload: &Workload<E>,
+    recorder: &mut Recorder<W>,
 ) -> io::Result<()>
 where
+    E: Element,
     W: io::Write,
 {
-    let oper_name = "find";
-
-    let target_elements = [
-        all_elements[all_elements.len() / 4],
-        all_elements[all_elements.len() / 2],
-        all_elements[3 * all_elements.len() / 4],
-        extra_element,
-    ];
-
-    let mut found_all = true;
-
-    found_all &= collections.good_local_array.record_find(
-        &target_elements,
-        all_elements,
-        mode_name,
-        oper_name,
-        csv_writer,
-    )?;
-
-    found_all &= collections.bad_local_array.record_find(
-        &target_elements,
-        all_elements,
-        mode_name,
-        oper_name,
-        csv_writer,
-    )?;
-
-    found_all &= collections.worse_local_array.record_find(
-        &target_elements,
-        all_elements,
-        mode_name,
-        oper_name,
-        csv_writer,
-    )?;
-
-    found_all &= collections.sorted_array.record_find(
-        &target_elements,
-        all_elements,
-        mode_name,
-        oper_name,
-        csv_writer,
-    )?;
-
-    found_all &= collections.with_order_tree.record_find(
-        &target_elements,
-        all_elements,
-        mode_name,
-        oper_name,
-        csv_writer,
-    )?;
-
-    found_all &= collections.without_order_tree.record_find(
-        &target_elements,
-        all_elements,
-        mode_name,
-        oper_name,
-        csv_writer,
-    )?;
-
-    found_all &= collections.linked_list.record_find(
-        &target_elements,
-        all_elements,
-        mode_name,
-        oper_name,
-        csv_writer,
-    )?;
-
-    println!("Found all? {:?}", found_all);
+    let mut found_all = vec![None; operations.len()];
+    for (_, new) in entries {
+        let mut runner = new();
+        for (&oper_name, found_all) in operations.iter().zip(&mut found_all) {
+            let record = recorded.contains(&oper_name);
+            let found = runner.run(oper_name, record, workload, recorder)?;
+            if let Some(found) = found {
+                *found_all = Some(found_all.unwrap_or(true) & found);
+            }
+        }
+    }
+    for found_all in found_all.into_iter().flatten() {
+        eprintln!("Found all? {:?}", found_all);
+    }
 
     Ok(())
 }
 
-fn run_inc_less_than<W>(
-    collections: &mut Collections,
-    all_elements: &[Element],
-    extra_element: Element,
-    mode_name: &str,
-    csv_writer: &mut csv::Writer<W>,
+// Enabled operations before the recorded one still run, unrecorded, so that
+// it finds the collection in the same state as in a whole run.
+fn run_cell<E, W>(
+    collection_name: &str,
+    oper_name: &str,
+    operations: &[&str],
+    workload: &Workload<E>,
+    recorder: &mut Recorder<W>,
 ) -> io::Result<()>
 where
+    E: Element,
     W: io::Write,
 {
-    let oper_name = "inc-less-than";
-
-    let target_elements = [
-        all_elements[all_elements.len() / 4],
-        all_elements[all_elements.len() / 2],
-        all_elements[3 * all_elements.len() / 4],
-        extra_element,
-    ];
-
-    collections.good_local_array.record_inc_less_than(
-        &target_elements,
-        all_elements,
-        mode_name,
-        oper_name,
-        csv_writer,
-    )?;
-
-    collections.bad_local_array.record_inc_less_than(
-        &target_elements,
-        all_elements,
-        mode_name,
-        oper_name,
-        csv_writer,
-    )?;
-
-    collections.worse_local_array.record_inc_less_than(
-        &target_elements,
-        all_elements,
-        mode_name,
-        oper_name,
-        csv_writer,
-    )?;
-
-    collections.sorted_array.record_inc_less_than(
-        &target_elements,
-        all_elements,
-        mode_name,
-        oper_name,
-        csv_writer,
-    )?;
-
-    collections.with_order_tree.record_inc_less_than(
-        &target_elements,
-        all_elements,
-        mode_name,
-        oper_name,
-        csv_writer,
-    )?;
-
-    collections.without_order_tree.record_inc_less_than(
-        &target_elements,
-        all_elements,
-        mode_name,
-        oper_name,
-        csv_writer,
-    )?;
-
-    collections.linked_list.record_inc_less_than(
-        &target_elements,
-        all_elements,
-        mode_name,
-        oper_name,
-        csv_writer,
-    )?;
-
-    Ok(())
+    let entry = registry::<E, W>()
+        .into_iter()
+        .find(|&(name, _)| name == collection_name);
+    let entry = match entry {
+        Some(entry) => entry,
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unknown collection {}", collection_name),
+        ))?,
+    };
+    let position = match operations.iter().position(|&name| name == oper_name)
+    {
+        Some(position) => position,
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unknown or disabled operation {}", oper_name),
+        ))?,
+    };
+    run_for_size(
+        &[entry],
+        &operations[..= position],
+        &[oper_name],
+        workload,
+        recorder,
+    )
 }
@@ -0,0 +1,233 @@
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+    process,
+    ptr,
+};
+
+fn cgroup2_mount() -> io::Result<PathBuf> {
+    let mounts = fs::read_to_string("/proc/self/mounts")?;
+    mounts
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .find(|fields| fields.get(2) == Some(&"cgroup2"))
+        .map(|fields| PathBuf::from(fields[1]))
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "cgroup v2 is not mounted")
+        })
+}
+
+// Memory limited cgroup that children join, inside a group of this process.
+// Cgroup v2 only enables controllers for the children of cgroups holding no
+// processes, so this process first leaves its cgroup for a leaf of the group.
+#[derive(Debug)]
+pub struct Cgroup {
+    parent: PathBuf,
+    group: PathBuf,
+    // Whether the memory controller was enabled in the parent by this
+    // process, and must be disabled again before returning to it.
+    enabled_in_parent: bool,
+}
+
+impl Cgroup {
+    pub fn create(limit: usize) -> io::Result<Self> {
+        let root = cgroup2_mount()?;
+        let own_cgroups = fs::read_to_string("/proc/self/cgroup")?;
+        let relative = own_cgroups
+            .lines()
+            .find_map(|line| line.strip_prefix("0::"))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "cgroup v2 is not mounted",
+                )
+            })?;
+        let parent = root.join(relative.trim_start_matches('/'));
+
+        let controllers =
+            fs::read_to_string(parent.join("cgroup.controllers"))?;
+        if !controllers.split_whitespace().any(|name| name == "memory") {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "memory controller is not available to this cgroup",
+            ))?;
+        }
+
+        let group = parent.join(format!("sisop1-trab2-{}", process::id()));
+        fs::create_dir(&group)?;
+        let mut this = Self { parent, group, enabled_in_parent: false };
+        if let Err(error) = this.set_up(limit) {
+            let _ = this.tear_down();
+            Err(error)?;
+        }
+        Ok(this)
+    }
+
+    // Cgroup limited to the given number of bytes.
+    pub fn path(&self) -> PathBuf {
+        self.group.join("limited")
+    }
+
+    fn set_up(&mut self, limit: usize) -> io::Result<()> {
+        let runner = self.group.join("runner");
+        fs::create_dir(&runner)?;
+        enter_cgroup(&runner)?;
+
+        let parent_control = self.parent.join("cgroup.subtree_control");
+        let enabled = fs::read_to_string(&parent_control)?;
+        if !enabled.split_whitespace().any(|name| name == "memory") {
+            fs::write(&parent_control, "+memory").map_err(|error| {
+                io::Error::new(
+                    error.kind(),
+                    format!(
+                        "cannot enable the memory controller of {}, which \
+                         may still hold other processes: {}",
+                        self.parent.display(),
+                        error
+                    ),
+                )
+            })?;
+            self.enabled_in_parent = true;
+        }
+        fs::write(self.group.join("cgroup.subtree_control"), "+memory")?;
+
+        let path = self.path();
+        fs::create_dir(&path)?;
+        fs::write(path.join("memory.max"), limit.to_string())
+    }
+
+    // Undoes as much of the set up as was done, in reverse.
+    fn tear_down(&self) -> io::Result<()> {
+        let path = self.path();
+        if path.exists() {
+            fs::remove_dir(&path)?;
+        }
+        fs::write(self.group.join("cgroup.subtree_control"), "-memory")?;
+        if self.enabled_in_parent {
+            let parent_control = self.parent.join("cgroup.subtree_control");
+            fs::write(parent_control, "-memory")?;
+        }
+        let runner = self.group.join("runner");
+        if runner.exists() {
+            enter_cgroup(&self.parent)?;
+            fs::remove_dir(&runner)?;
+        }
+        fs::remove_dir(&self.group)
+    }
+
+    // Moves this process back to its cgroup.
+    pub fn remove(self) -> io::Result<()> {
+        self.tear_down()
+    }
+}
+
+pub fn enter_cgroup(path: &Path) -> io::Result<()> {
+    fs::write(path.join("cgroup.procs"), process::id().to_string())
+}
+
+pub fn oom_kills(path: &Path) -> io::Result<u64> {
+    let events = fs::read_to_string(path.join("memory.events"))?;
+    events
+        .lines()
+        .find_map(|line| line.strip_prefix("oom_kill "))
+        .and_then(|count| count.trim().parse().ok())
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "no oom_kill event")
+        })
+}
+
+// Capability bit allowing to lock any amount of memory.
+const CAP_IPC_LOCK: u32 = 14;
+
+fn available_memory() -> io::Result<usize> {
+    let meminfo = fs::read_to_string("/proc/meminfo")?;
+    let kibibytes: usize = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))
+        .and_then(|value| value.trim_end_matches("kB").trim().parse().ok())
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "no MemAvailable")
+        })?;
+    Ok(kibibytes * 1024)
+}
+
+#[derive(Debug)]
+pub struct Ballast {
+    address: *mut libc::c_void,
+    length: usize,
+}
+
+fn can_lock_any_memory() -> io::Result<bool> {
+    let status = fs::read_to_string("/proc/self/status")?;
+    let capabilities = status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|value| u64::from_str_radix(value.trim(), 16).ok())
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "no CapEff")
+        })?;
+    Ok(capabilities & 1 << CAP_IPC_LOCK != 0)
+}
+
+impl Ballast {
+    // Whether a ballast leaving `limit` bytes available could be locked now,
+    // so that children do not each fail to.
+    pub fn check(limit: usize) -> io::Result<()> {
+        let length = available_memory()?.saturating_sub(limit);
+        let mut rlimit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+        if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut rlimit) } < 0 {
+            Err(io::Error::last_os_error())?;
+        }
+        let allowed = rlimit.rlim_cur == libc::RLIM_INFINITY
+            || rlimit.rlim_cur >= length as libc::rlim_t;
+        if allowed || can_lock_any_memory()? {
+            return Ok(());
+        }
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "locking {} bytes needs CAP_IPC_LOCK or a RLIMIT_MEMLOCK at \
+                 least as large, not {} bytes",
+                length, rlimit.rlim_cur
+            ),
+        ))
+    }
+
+    pub fn reserve(limit: usize) -> io::Result<Self> {
+        let length = available_memory()?.saturating_sub(limit);
+        if length == 0 {
+            return Ok(Self { address: ptr::null_mut(), length });
+        }
+
+        let address = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                length,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if address == libc::MAP_FAILED {
+            Err(io::Error::last_os_error())?;
+        }
+
+        let this = Self { address, length };
+        if unsafe { libc::mlock(address, length) } < 0 {
+            Err(io::Error::last_os_error())?;
+        }
+        Ok(this)
+    }
+}
+
+impl Drop for Ballast {
+    fn drop(&mut self) {
+        if self.length > 0 {
+            unsafe {
+                libc::munmap(self.address, self.length);
+            }
+        }
+    }
+}
@@ -0,0 +1,469 @@
+use crate::{element::Element, residency};
+use std::{
+    error::Error,
+    fmt,
+    fs,
+    io,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+    process,
+    ptr,
+    slice,
+    str::FromStr,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+static FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+// Set once mapping with huge pages has failed and a warning was printed.
+static HUGE_PAGES_FAILED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone)]
+pub struct AdviceError;
+
+impl fmt::Display for AdviceError {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmtr,
+            "Advice must be one of sequential, random, will-need, huge-page or \
+             no-huge-page"
+        )
+    }
+}
+
+impl Error for AdviceError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Advice {
+    Sequential,
+    Random,
+    WillNeed,
+    HugePage,
+    NoHugePage,
+}
+
+impl Advice {
+    fn to_raw(self) -> libc::c_int {
+        match self {
+            Advice::Sequential => libc::MADV_SEQUENTIAL,
+            Advice::Random => libc::MADV_RANDOM,
+            Advice::WillNeed => libc::MADV_WILLNEED,
+            Advice::HugePage => libc::MADV_HUGEPAGE,
+            Advice::NoHugePage => libc::MADV_NOHUGEPAGE,
+        }
+    }
+}
+
+impl FromStr for Advice {
+    type Err = AdviceError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "sequential" => Ok(Advice::Sequential),
+            "random" => Ok(Advice::Random),
+            "will-need" => Ok(Advice::WillNeed),
+            "huge-page" => Ok(Advice::HugePage),
+            "no-huge-page" => Ok(Advice::NoHugePage),
+            _ => Err(AdviceError),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HugePagesError;
+
+impl fmt::Display for HugePagesError {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmtr, "Huge pages must be either thp or hugetlb")
+    }
+}
+
+impl Error for HugePagesError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HugePages {
+    Transparent,
+    HugeTlb,
+}
+
+impl HugePages {
+    pub fn is_available(self) -> bool {
+        match self {
+            HugePages::Transparent => {
+                fs::read_to_string(THP_DIR.to_owned() + "/enabled")
+                    .is_ok_and(|enabled| !enabled.contains("[never]"))
+            },
+            HugePages::HugeTlb => {
+                meminfo_field("HugePages_Free:").is_some_and(|free| free > 0)
+            },
+        }
+    }
+
+    pub fn page_size(self) -> usize {
+        match self {
+            HugePages::Transparent => {
+                fs::read_to_string(THP_DIR.to_owned() + "/hpage_pmd_size")
+                    .ok()
+                    .and_then(|size| size.trim().parse().ok())
+                    .unwrap_or_else(residency::system_page_size)
+            },
+            HugePages::HugeTlb => meminfo_field("Hugepagesize:")
+                .map_or_else(residency::system_page_size, |size| size * 1024),
+        }
+    }
+}
+
+impl FromStr for HugePages {
+    type Err = HugePagesError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "thp" => Ok(HugePages::Transparent),
+            "hugetlb" => Ok(HugePages::HugeTlb),
+            _ => Err(HugePagesError),
+        }
+    }
+}
+
+pub const THP_DIR: &str = "/sys/kernel/mm/transparent_hugepage";
+
+fn meminfo_field(name: &str) -> Option<usize> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix(name))
+        .and_then(|value| value.trim_end_matches("kB").trim().parse().ok())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MapOptions {
+    // Backs the mapping with an unlinked file in this directory instead of
+    // anonymous memory.
+    pub file_dir: Option<PathBuf>,
+    pub advice: Option<Advice>,
+    pub populate: bool,
+    pub huge_pages: Option<HugePages>,
+}
+
+impl MapOptions {
+    pub fn page_size(&self) -> usize {
+        match self.huge_pages {
+            Some(HugePages::HugeTlb) => HugePages::HugeTlb.page_size(),
+            _ => residency::system_page_size(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Region {
+    address: *mut u8,
+    length: usize,
+}
+
+impl Region {
+    // Huge pages may run out after they were found available, in which case
+    // regular pages are mapped instead.
+    pub fn map(
+        length: usize,
+        file: Option<&fs::File>,
+        options: &MapOptions,
+    ) -> io::Result<Self> {
+        let huge_pages = match options.huge_pages {
+            Some(huge_pages) => huge_pages,
+            None => return Self::map_with(length, file, options),
+        };
+        match Self::map_with(length, file, options) {
+            Ok(this) => Ok(this),
+            Err(error) => {
+                if !HUGE_PAGES_FAILED.swap(true, Ordering::Relaxed) {
+                    eprintln!(
+                        "Warning: cannot map with {:?} huge pages ({}), \
+                         falling back to regular pages",
+                        huge_pages, error
+                    );
+                }
+                let regular =
+                    MapOptions { huge_pages: None, ..options.clone() };
+                Self::map_with(length, file, &regular)
+            },
+        }
+    }
+
+    fn map_with(
+        length: usize,
+        file: Option<&fs::File>,
+        options: &MapOptions,
+    ) -> io::Result<Self> {
+        let page_size = options.page_size();
+        let length = length.max(1).div_ceil(page_size) * page_size;
+
+        let mut flags = 0;
+        let mut fd = -1;
+        match file {
+            Some(file) => {
+                file.set_len(length as u64)?;
+                flags |= libc::MAP_SHARED;
+                fd = file.as_raw_fd();
+            },
+            None => flags |= libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+        }
+        if options.populate {
+            flags |= libc::MAP_POPULATE;
+        }
+        if options.huge_pages == Some(HugePages::HugeTlb) {
+            flags |= libc::MAP_HUGETLB;
+        }
+
+        let address = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                length,
+                libc::PROT_READ | libc::PROT_WRITE,
+                flags,
+                fd,
+                0,
+            )
+        };
+        if address == libc::MAP_FAILED {
+            Err(io::Error::last_os_error())?;
+        }
+        let this = Self { address: address as *mut u8, length };
+
+        if options.huge_pages == Some(HugePages::Transparent) {
+            this.advise(libc::MADV_HUGEPAGE)?;
+        }
+        if let Some(advice) = options.advice {
+            this.advise(advice.to_raw())?;
+        }
+
+        Ok(this)
+    }
+
+    fn advise(&self, advice: libc::c_int) -> io::Result<()> {
+        let address = self.address as *mut libc::c_void;
+        if unsafe { libc::madvise(address, self.length, advice) } < 0 {
+            Err(io::Error::last_os_error())?;
+        }
+        Ok(())
+    }
+
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.address
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+}
+
+impl Drop for Region {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.address as *mut libc::c_void, self.length);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MappedBuffer<E> {
+    region: Option<Region>,
+    length: usize,
+    file: Option<fs::File>,
+    options: MapOptions,
+    _elements: PhantomData<E>,
+}
+
+impl<E> MappedBuffer<E>
+where
+    E: Element,
+{
+    pub fn new(options: MapOptions) -> io::Result<Self> {
+        let file = match &options.file_dir {
+            Some(dir) => Some(open_unlinked(dir).map_err(|error| {
+                io::Error::new(
+                    error.kind(),
+                    format!(
+                        "cannot create mmap backing file in {}: {}",
+                        dir.display(),
+                        error
+                    ),
+                )
+            })?),
+            None => None,
+        };
+        Ok(Self {
+            region: None,
+            length: 0,
+            file,
+            options,
+            _elements: PhantomData,
+        })
+    }
+
+    fn as_ptr(&self) -> *mut E {
+        match &self.region {
+            Some(region) => region.as_ptr() as *mut E,
+            None => ptr::NonNull::dangling().as_ptr(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.region
+            .as_ref()
+            .map_or(0, |region| region.len() / mem::size_of::<E>())
+    }
+
+    pub fn push(&mut self, element: E) -> io::Result<()> {
+        if self.length == self.capacity() {
+            self.grow((self.capacity() * 2).max(1))?;
+        }
+        unsafe {
+            self.as_ptr().add(self.length).write(element);
+        }
+        self.length += 1;
+        Ok(())
+    }
+
+    fn grow(&mut self, new_capacity: usize) -> io::Result<()> {
+        let bytes = new_capacity * mem::size_of::<E>();
+        let new_region = Region::map(bytes, self.file.as_ref(), &self.options)
+            .map_err(|error| {
+                io::Error::new(
+                    error.kind(),
+                    format!("cannot map {} elements: {}", new_capacity, error),
+                )
+            })?;
+        // A shared file mapping already sees the elements written through
+        // the old one.
+        if self.file.is_none() {
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    self.as_ptr(),
+                    new_region.as_ptr() as *mut E,
+                    self.length,
+                );
+            }
+        }
+        self.region = Some(new_region);
+        Ok(())
+    }
+}
+
+fn open_unlinked(dir: &Path) -> io::Result<fs::File> {
+    let name = format!(
+        "sisop1-trab2-{}-{}",
+        process::id(),
+        FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    let path = dir.join(name);
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    fs::remove_file(&path)?;
+    Ok(file)
+}
+
+impl<E> Deref for MappedBuffer<E>
+where
+    E: Element,
+{
+    type Target = [E];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.length) }
+    }
+}
+
+impl<E> DerefMut for MappedBuffer<E>
+where
+    E: Element,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { slice::from_raw_parts_mut(self.as_ptr(), self.length) }
+    }
+}
+
+impl<E> Clone for MappedBuffer<E>
+where
+    E: Element,
+{
+    // Mapping with the same options already worked for the original buffer.
+    fn clone(&self) -> Self {
+        let mut new_buffer = Self::new(self.options.clone())
+            .unwrap_or_else(|error| panic!("cannot clone buffer: {}", error));
+        for &element in self.iter() {
+            new_buffer.push(element).unwrap_or_else(|error| {
+                panic!("cannot clone buffer: {}", error)
+            });
+        }
+        new_buffer
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Advice, HugePages, MapOptions, MappedBuffer};
+    use crate::element::Element;
+    use std::env;
+
+    const ELEMS_IN_PAGE: usize = u64::ELEMS_IN_PAGE;
+
+    #[test]
+    fn anonymous() {
+        let options = MapOptions {
+            file_dir: None,
+            advice: Some(Advice::Random),
+            populate: true,
+            huge_pages: None,
+        };
+        let mut buffer = MappedBuffer::new(options).unwrap();
+        for i in 0 .. ELEMS_IN_PAGE * 3 + 1 {
+            buffer.push(i as u64).unwrap();
+        }
+        buffer[0] = 7;
+
+        let cloned = buffer.clone();
+        assert_eq!(cloned.len(), ELEMS_IN_PAGE * 3 + 1);
+        assert_eq!(cloned[0], 7);
+        assert!(cloned[1 ..]
+            .iter()
+            .enumerate()
+            .all(|(i, &element)| element == i as u64 + 1));
+    }
+
+    #[test]
+    fn file_backed() {
+        let options = MapOptions {
+            file_dir: Some(env::temp_dir()),
+            advice: Some(Advice::Sequential),
+            populate: false,
+            huge_pages: None,
+        };
+        let mut buffer = MappedBuffer::new(options).unwrap();
+        for i in 0 .. ELEMS_IN_PAGE * 2 + 5 {
+            buffer.push(i as u64).unwrap();
+        }
+        buffer.sort_by(|a, b| b.cmp(a));
+
+        assert_eq!(buffer.len(), ELEMS_IN_PAGE * 2 + 5);
+        assert_eq!(buffer[0], (ELEMS_IN_PAGE * 2 + 4) as u64);
+        assert_eq!(buffer[buffer.len() - 1], 0);
+    }
+
+    #[test]
+    fn huge_pages_fall_back() {
+        let options = MapOptions {
+            huge_pages: Some(HugePages::HugeTlb),
+            ..MapOptions::default()
+        };
+        let mut buffer = MappedBuffer::new(options).unwrap();
+        for i in 0 .. ELEMS_IN_PAGE + 1 {
+            buffer.push(i as u64).unwrap();
+        }
+        assert_eq!(buffer[ELEMS_IN_PAGE], ELEMS_IN_PAGE as u64);
+    }
+}
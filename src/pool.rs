@@ -0,0 +1,478 @@
+use crate::{
+    allocator,
+    mmap::{MapOptions, Region},
+    PAGE_SIZE,
+};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use std::{
+    alloc::{self, Layout},
+    cell::RefCell,
+    error::Error,
+    fmt,
+    io,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut, Range},
+    ptr::{self, NonNull},
+    str::FromStr,
+};
+
+// Set in the pointer of nodes living in a pool, which are dropped in place
+// instead of being deallocated.
+const POOLED_TAG: usize = 1;
+
+thread_local! {
+    // Addresses and slot size of every live pool, so that pooled nodes are
+    // counted as the whole slot they take.
+    static POOLS: RefCell<Vec<(Range<usize>, usize)>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+// Size of the slot at the address, in a live pool.
+fn slot_size(address: usize) -> usize {
+    POOLS.with(|pools| {
+        let pools = pools.borrow();
+        let pool = pools.iter().find(|(range, _)| range.contains(&address));
+        pool.expect("pooled node outlives its pool").1
+    })
+}
+
+pub struct NodeBox<T> {
+    pointer: NonNull<T>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> NodeBox<T> {
+    pub fn new(value: T) -> Self {
+        let pointer = Box::into_raw(Box::new(value));
+        Self {
+            pointer: unsafe { NonNull::new_unchecked(pointer) },
+            _marker: PhantomData,
+        }
+    }
+
+    fn is_pooled(&self) -> bool {
+        self.pointer.as_ptr() as usize & POOLED_TAG != 0
+    }
+
+    pub fn allocated_size(&self) -> usize {
+        if self.is_pooled() {
+            slot_size(self.as_ptr() as usize)
+        } else {
+            heap_allocated_size(self.as_ptr())
+        }
+    }
+
+    fn as_ptr(&self) -> *mut T {
+        (self.pointer.as_ptr() as usize & !POOLED_TAG) as *mut T
+    }
+
+    // Gives up ownership, taken back with `NodePtr::into_box`.
+    pub fn into_node_ptr(self) -> NodePtr<T> {
+        let node = NodePtr { pointer: self.pointer };
+        mem::forget(self);
+        node
+    }
+}
+
+// Node of a `NodeBox` linked without owning it, for lists reaching their
+// nodes from both sides. Whoever unlinks the node last frees it.
+#[derive(Debug)]
+pub struct NodePtr<T> {
+    pointer: NonNull<T>,
+}
+
+impl<T> NodePtr<T> {
+    // Distinct for every node, also pooled ones, and turned back into the
+    // node by `from_address`.
+    pub fn address(self) -> usize {
+        self.pointer.as_ptr() as usize
+    }
+
+    pub fn from_address(address: usize) -> Option<Self> {
+        NonNull::new(address as *mut T).map(|pointer| Self { pointer })
+    }
+
+    pub fn as_ptr(self) -> *mut T {
+        (self.pointer.as_ptr() as usize & !POOLED_TAG) as *mut T
+    }
+
+    // The node must outlive the reference.
+    pub unsafe fn as_ref<'node>(self) -> &'node T {
+        &*self.as_ptr()
+    }
+
+    pub fn allocated_size(self) -> usize {
+        mem::ManuallyDrop::new(NodeBox {
+            pointer: self.pointer,
+            _marker: PhantomData,
+        })
+        .allocated_size()
+    }
+
+    // No other copy of the pointer may be used afterwards.
+    pub unsafe fn into_box(self) -> NodeBox<T> {
+        NodeBox { pointer: self.pointer, _marker: PhantomData }
+    }
+}
+
+impl<T> Clone for NodePtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for NodePtr<T> {}
+
+impl<T> PartialEq for NodePtr<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.pointer == other.pointer
+    }
+}
+
+impl<T> Eq for NodePtr<T> {}
+
+// Block of a layout only known at run time, for nodes sized by an option.
+// Its owner keeps the layout and frees it, which does nothing for blocks in
+// a pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockPtr {
+    pointer: NonNull<u8>,
+}
+
+impl BlockPtr {
+    pub fn alloc(layout: Layout) -> Self {
+        let pointer = unsafe { alloc::alloc(layout) };
+        match NonNull::new(pointer) {
+            Some(pointer) => Self { pointer },
+            None => alloc::handle_alloc_error(layout),
+        }
+    }
+
+    // Placeholder for a block yet to be allocated, never to be used.
+    pub fn dangling() -> Self {
+        Self { pointer: NonNull::dangling() }
+    }
+
+    fn is_pooled(self) -> bool {
+        self.pointer.as_ptr() as usize & POOLED_TAG != 0
+    }
+
+    pub fn as_ptr(self) -> *mut u8 {
+        (self.pointer.as_ptr() as usize & !POOLED_TAG) as *mut u8
+    }
+
+    pub fn allocated_size(self) -> usize {
+        if self.is_pooled() {
+            slot_size(self.as_ptr() as usize)
+        } else {
+            heap_allocated_size(self.as_ptr())
+        }
+    }
+
+    // The block must have been allocated with this layout and not be used
+    // afterwards.
+    pub unsafe fn free(self, layout: Layout) {
+        if !self.is_pooled() {
+            alloc::dealloc(self.as_ptr(), layout);
+        }
+    }
+}
+
+// Blocks of nodes sized by an option are aligned to their size, up to a
+// page, so that they start at cache lines.
+pub fn block_layout(size: usize, min_align: usize) -> Layout {
+    let align = (1 << size.trailing_zeros()).min(PAGE_SIZE).max(min_align);
+    Layout::from_size_align(size, align).expect("node size overflows")
+}
+
+// Bytes taken by a heap allocation, counting the size word glibc keeps before
+// every chunk, but not the stamp of the counting allocator.
+pub fn heap_allocated_size<T>(pointer: *const T) -> usize {
+    let usable =
+        unsafe { libc::malloc_usable_size(pointer as *mut libc::c_void) };
+    usable + mem::size_of::<usize>() - allocator::stamp_size()
+}
+
+impl<T> Deref for NodeBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.as_ptr() }
+    }
+}
+
+impl<T> DerefMut for NodeBox<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.as_ptr() }
+    }
+}
+
+impl<T> fmt::Debug for NodeBox<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, fmtr)
+    }
+}
+
+impl<T> Drop for NodeBox<T> {
+    fn drop(&mut self) {
+        if self.is_pooled() {
+            unsafe { ptr::drop_in_place(self.as_ptr()) }
+        } else {
+            drop(unsafe { Box::from_raw(self.as_ptr()) });
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlacementError;
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, fmtr: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmtr,
+            "Placement must be one of sequential, reversed, shuffled or \
+             page-strided"
+        )
+    }
+}
+
+impl Error for PlacementError {}
+
+// Order in which a pool hands out its slots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Sequential,
+    Reversed,
+    Shuffled,
+    // Sequential, but every slot takes a whole page.
+    PageStrided,
+}
+
+impl FromStr for Placement {
+    type Err = PlacementError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "sequential" => Ok(Placement::Sequential),
+            "reversed" => Ok(Placement::Reversed),
+            "shuffled" => Ok(Placement::Shuffled),
+            "page-strided" => Ok(Placement::PageStrided),
+            _ => Err(PlacementError),
+        }
+    }
+}
+
+// Slots of a pool are never reused, and the pool must outlive every node
+// allocated from it.
+#[derive(Debug)]
+pub struct Pool {
+    region: Region,
+    slot_layout: Layout,
+    slots: usize,
+    next: usize,
+    placement: Placement,
+    // Slot handed out for each allocation of a shuffled pool.
+    permutation: Vec<usize>,
+}
+
+impl Pool {
+    pub fn new(
+        slot_layout: Layout,
+        slots: usize,
+        options: &MapOptions,
+    ) -> io::Result<Self> {
+        Self::with_placement(
+            slot_layout,
+            slots,
+            options,
+            Placement::Sequential,
+            [0; 32],
+        )
+    }
+
+    // The seed only matters to shuffled pools.
+    pub fn with_placement(
+        slot_layout: Layout,
+        slots: usize,
+        options: &MapOptions,
+        placement: Placement,
+        seed: [u8; 32],
+    ) -> io::Result<Self> {
+        let mut slot_layout = slot_layout.pad_to_align();
+        if placement == Placement::PageStrided {
+            let page_size = options.page_size();
+            let size = slot_layout.size().div_ceil(page_size) * page_size;
+            slot_layout = Layout::from_size_align(size, slot_layout.align())
+                .map_err(io::Error::other)?;
+        }
+        let region = Region::map(slot_layout.size() * slots, None, options)?;
+        let start = region.as_ptr() as usize;
+        POOLS.with(|pools| {
+            let range = start .. start + region.len();
+            pools.borrow_mut().push((range, slot_layout.size()));
+        });
+
+        let mut permutation = Vec::new();
+        if placement == Placement::Shuffled {
+            permutation.extend(0 .. slots);
+            permutation.shuffle(&mut StdRng::from_seed(seed));
+        }
+
+        Ok(Self {
+            region,
+            slot_layout,
+            slots,
+            next: 0,
+            placement,
+            permutation,
+        })
+    }
+
+    // Falls back to the heap once the pool is exhausted. The pool must
+    // outlive the node, which points into it.
+    pub unsafe fn alloc<T>(&mut self, value: T) -> NodeBox<T> {
+        match self.next_slot(Layout::new::<T>()) {
+            Some(pointer) => unsafe {
+                let pointer = pointer.as_ptr() as *mut T;
+                pointer.write(value);
+                let tagged = (pointer as usize | POOLED_TAG) as *mut T;
+                NodeBox {
+                    pointer: NonNull::new_unchecked(tagged),
+                    _marker: PhantomData,
+                }
+            },
+            None => NodeBox::new(value),
+        }
+    }
+
+    // Uninitialized, and also from the heap once the pool is exhausted. The
+    // pool must outlive the block, as for `alloc`.
+    pub unsafe fn alloc_block(&mut self, layout: Layout) -> BlockPtr {
+        match self.next_slot(layout) {
+            Some(pointer) => BlockPtr {
+                pointer: pointer.map_addr(|address| address | POOLED_TAG),
+            },
+            None => BlockPtr::alloc(layout),
+        }
+    }
+
+    fn next_slot(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        assert!(layout.size() <= self.slot_layout.size());
+        assert!(layout.align() <= self.slot_layout.align());
+        assert!(layout.align() > POOLED_TAG);
+
+        if self.next == self.slots {
+            return None;
+        }
+        let slot = match self.placement {
+            Placement::Sequential | Placement::PageStrided => self.next,
+            Placement::Reversed => self.slots - 1 - self.next,
+            Placement::Shuffled => self.permutation[self.next],
+        };
+        let offset = slot * self.slot_layout.size();
+        self.next += 1;
+        NonNull::new(unsafe { self.region.as_ptr().add(offset) })
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        let start = self.region.as_ptr() as usize;
+        POOLS.with(|pools| {
+            pools.borrow_mut().retain(|(range, _)| range.start != start)
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{NodeBox, Placement, Pool};
+    use crate::{mmap::MapOptions, residency};
+    use std::{alloc::Layout, rc::Rc};
+
+    #[test]
+    fn alloc() {
+        let layout = Layout::new::<(u64, u64)>();
+        let mut pool = Pool::new(layout, 2, &MapOptions::default()).unwrap();
+
+        // Every node is dropped before the pool.
+        let (mut first, second, third) = unsafe {
+            (
+                pool.alloc((1u64, 2u64)),
+                pool.alloc((3u64, 4u64)),
+                pool.alloc((5u64, 6u64)),
+            )
+        };
+        first.1 = 7;
+
+        assert_eq!(*first, (1, 7));
+        assert_eq!(*second, (3, 4));
+        assert_eq!(*third, (5, 6));
+        assert!(first.is_pooled());
+        assert!(second.is_pooled());
+        assert!(!third.is_pooled());
+        assert_eq!(
+            &*second as *const _ as usize - &*first as *const _ as usize,
+            layout.size()
+        );
+    }
+
+    #[test]
+    fn placements() {
+        let layout = Layout::new::<u64>();
+        let options = MapOptions::default();
+        let slot_addresses = |placement| {
+            let mut pool =
+                Pool::with_placement(layout, 8, &options, placement, [1; 32])
+                    .unwrap();
+            let base = pool.region.as_ptr() as usize;
+            (0 .. 8)
+                .map(|i| unsafe { &*pool.alloc(i as u64) as *const u64 })
+                .map(|node| node as usize - base)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            slot_addresses(Placement::Reversed),
+            (0 .. 8).rev().map(|slot| slot * 8).collect::<Vec<_>>()
+        );
+        let page_size = residency::system_page_size();
+        assert_eq!(
+            slot_addresses(Placement::PageStrided),
+            (0 .. 8).map(|slot| slot * page_size).collect::<Vec<_>>()
+        );
+        let mut pool = Pool::with_placement(
+            layout,
+            1,
+            &options,
+            Placement::PageStrided,
+            [0; 32],
+        )
+        .unwrap();
+        let node = unsafe { pool.alloc(0u64) };
+        assert_eq!(node.allocated_size(), page_size);
+        drop(node);
+
+        let mut shuffled = slot_addresses(Placement::Shuffled);
+        shuffled.sort_unstable();
+        assert_eq!(shuffled, (0 .. 8).map(|slot| slot * 8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn drop_in_place() {
+        let counter = Rc::new(());
+        let layout = Layout::new::<Rc<()>>();
+        let mut pool = Pool::new(layout, 1, &MapOptions::default()).unwrap();
+
+        let pooled = unsafe { pool.alloc(counter.clone()) };
+        let boxed = NodeBox::new(counter.clone());
+        assert_eq!(Rc::strong_count(&counter), 3);
+        drop(pooled);
+        drop(boxed);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+}
@@ -0,0 +1,760 @@
+use crate::{
+    allocator::{AllocationStats, ALLOCATOR},
+    cache::{self, CacheState, Evictor},
+    clock::{Clock, ClockSource},
+    collection::Collection,
+    element::Element,
+    residency::{self, PageSet},
+    reuse_distance::ReuseProfiler,
+    trace,
+};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    mem,
+    os::unix::io::FromRawFd,
+    panic::{self, AssertUnwindSafe},
+};
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+struct RecordRow<'mode, 'oper> {
+    mode: &'mode str,
+    size: usize,
+    operation: &'oper str,
+    collection: &'static str,
+    nanoseconds: u128,
+    page_size: usize,
+    cache_state: Option<&'static str>,
+}
+
+// A cell whose child process failed has no time, and says why it failed.
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+struct FailedRow<'mode, 'oper> {
+    mode: &'mode str,
+    size: usize,
+    operation: &'oper str,
+    collection: &'oper str,
+    nanoseconds: Option<u128>,
+    page_size: usize,
+    cache_state: Option<&'static str>,
+    failure: &'oper str,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+struct TimesRow<'mode, 'oper> {
+    mode: &'mode str,
+    size: usize,
+    operation: &'oper str,
+    collection: &'static str,
+    wall_nanoseconds: u64,
+    cpu_nanoseconds: u64,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+struct FootprintRow<'mode, 'oper> {
+    mode: &'mode str,
+    size: usize,
+    operation: &'oper str,
+    collection: &'static str,
+    payload_bytes: usize,
+    overhead_bytes: usize,
+    rss_growth: i64,
+    pages: usize,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+struct HeightRow<'mode> {
+    mode: &'mode str,
+    size: usize,
+    collection: &'static str,
+    height: usize,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+struct AllocationsRow<'mode, 'oper> {
+    mode: &'mode str,
+    size: usize,
+    operation: &'oper str,
+    collection: &'static str,
+    allocations: u64,
+    reallocations: u64,
+    frees: u64,
+    allocated_bytes: u64,
+    freed_bytes: u64,
+    peak_live_bytes: i64,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+struct AllocationSizeRow<'mode, 'oper> {
+    mode: &'mode str,
+    size: usize,
+    operation: &'oper str,
+    collection: &'static str,
+    size_class: usize,
+    count: u64,
+    frees: u64,
+    mean_lifetime: f64,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+struct ReuseRow<'mode, 'oper> {
+    mode: &'mode str,
+    size: usize,
+    operation: &'oper str,
+    collection: &'static str,
+    granularity: usize,
+    distance: Option<usize>,
+    count: u64,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+struct ResidencyRow<'mode, 'oper> {
+    mode: &'mode str,
+    size: usize,
+    operation: &'oper str,
+    collection: &'static str,
+    pages: usize,
+    resident_pages: usize,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+struct FaultsRow<'mode, 'oper> {
+    mode: &'mode str,
+    size: usize,
+    operation: &'oper str,
+    collection: &'static str,
+    minor_faults: i64,
+    major_faults: i64,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+struct ForkRow<'mode, 'oper> {
+    mode: &'mode str,
+    size: usize,
+    operation: &'oper str,
+    collection: &'static str,
+    nanoseconds: u64,
+    minor_faults: i64,
+    major_faults: i64,
+    parent_rss_growth: i64,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+struct MetadataRow<'mode, 'entry> {
+    mode: &'mode str,
+    key: &'entry str,
+    value: &'entry str,
+}
+
+pub fn write_metadata<W>(
+    writer: &mut csv::Writer<W>,
+    mode_name: &str,
+    entries: &[(&str, String)],
+) -> io::Result<()>
+where
+    W: io::Write,
+{
+    for (key, value) in entries {
+        writer.serialize(MetadataRow { mode: mode_name, key, value })?;
+    }
+    writer.flush()
+}
+
+// Clocks read through `clock_gettime` are always available.
+fn builtin_clock(source: ClockSource) -> Clock {
+    Clock::new(source).expect("clock_gettime clocks are always available")
+}
+
+fn page_faults() -> (i64, i64) {
+    let mut usage: libc::rusage = unsafe { mem::zeroed() };
+    unsafe {
+        libc::getrusage(libc::RUSAGE_SELF, &mut usage);
+    }
+    (usage.ru_minflt, usage.ru_majflt)
+}
+
+#[derive(Debug)]
+pub struct Recorder<'mode, W>
+where
+    W: io::Write,
+{
+    mode_name: &'mode str,
+    page_size: usize,
+    cache_state: Option<CacheState>,
+    page_out: bool,
+    evictor: Option<Evictor>,
+    clock: Clock,
+    wall_clock: Clock,
+    cpu_clock: Clock,
+    csv_writer: csv::Writer<W>,
+    times_writer: Option<csv::Writer<W>>,
+    footprint_writer: Option<csv::Writer<W>>,
+    height_writer: Option<csv::Writer<W>>,
+    allocations_writer: Option<csv::Writer<W>>,
+    allocation_sizes_writer: Option<csv::Writer<W>>,
+    reuse_writer: Option<csv::Writer<W>>,
+    residency_writer: Option<csv::Writer<W>>,
+    faults_writer: Option<csv::Writer<W>>,
+    fork_writer: Option<csv::Writer<W>>,
+}
+
+impl<'mode, W> Recorder<'mode, W>
+where
+    W: io::Write,
+{
+    pub fn new(mode_name: &'mode str, csv_writer: csv::Writer<W>) -> Self {
+        Self {
+            mode_name,
+            page_size: residency::system_page_size(),
+            cache_state: None,
+            page_out: false,
+            evictor: None,
+            clock: builtin_clock(ClockSource::Monotonic),
+            wall_clock: builtin_clock(ClockSource::Monotonic),
+            cpu_clock: builtin_clock(ClockSource::ThreadCpuTime),
+            csv_writer,
+            times_writer: None,
+            footprint_writer: None,
+            height_writer: None,
+            allocations_writer: None,
+            allocation_sizes_writer: None,
+            reuse_writer: None,
+            residency_writer: None,
+            faults_writer: None,
+            fork_writer: None,
+        }
+    }
+
+    // Effective page size backing the collections, recorded in every row.
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    // Cache state set up before every operation, where cold may also page
+    // out the pages of the collection.
+    pub fn cache_state(
+        mut self,
+        cache_state: CacheState,
+        page_out: bool,
+    ) -> Self {
+        self.cache_state = Some(cache_state);
+        self.page_out = page_out;
+        self
+    }
+
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    // Also records wall and thread CPU time of every operation.
+    pub fn times(mut self, times_writer: csv::Writer<W>) -> Self {
+        self.times_writer = Some(times_writer);
+        self
+    }
+
+    pub fn footprint(mut self, footprint_writer: csv::Writer<W>) -> Self {
+        self.footprint_writer = Some(footprint_writer);
+        self
+    }
+
+    pub fn heights(mut self, height_writer: csv::Writer<W>) -> Self {
+        self.height_writer = Some(height_writer);
+        self
+    }
+
+    // Allocation counts only come from the global counting allocator.
+    pub fn allocations(mut self, allocations_writer: csv::Writer<W>) -> Self {
+        self.allocations_writer = Some(allocations_writer);
+        self
+    }
+
+    pub fn allocation_sizes(
+        mut self,
+        allocation_sizes_writer: csv::Writer<W>,
+    ) -> Self {
+        self.allocation_sizes_writer = Some(allocation_sizes_writer);
+        self
+    }
+
+    pub fn reuse_distance(mut self, reuse_writer: csv::Writer<W>) -> Self {
+        self.reuse_writer = Some(reuse_writer);
+        self
+    }
+
+    pub fn residency(mut self, residency_writer: csv::Writer<W>) -> Self {
+        self.residency_writer = Some(residency_writer);
+        self
+    }
+
+    pub fn faults(mut self, faults_writer: csv::Writer<W>) -> Self {
+        self.faults_writer = Some(faults_writer);
+        self
+    }
+
+    pub fn fork_cow(mut self, fork_writer: csv::Writer<W>) -> Self {
+        self.fork_writer = Some(fork_writer);
+        self
+    }
+
+    pub fn records_allocations(&self) -> bool {
+        self.allocations_writer.is_some()
+            || self.allocation_sizes_writer.is_some()
+    }
+
+    // Puts caches in the chosen state for the pages given by `storage`.
+    pub fn prepare_cache<F>(&mut self, storage: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut PageSet),
+    {
+        let cache_state = match self.cache_state {
+            Some(cache_state) => cache_state,
+            None => return Ok(()),
+        };
+
+        let mut pages = PageSet::new(residency::system_page_size());
+        storage(&mut pages);
+        match cache_state {
+            CacheState::Cold => {
+                if self.page_out {
+                    self.page_out = self.try_page_out(&mut pages)?;
+                }
+                self.evictor.get_or_insert_with(Evictor::new).evict();
+            },
+            CacheState::Warm => cache::warm(&mut pages),
+        }
+        Ok(())
+    }
+
+    // Kernels older than 5.4 reject `MADV_PAGEOUT`, after which caches are
+    // only evicted. Returns whether paging out is still possible.
+    fn try_page_out(&self, pages: &mut PageSet) -> io::Result<bool> {
+        match cache::page_out(pages) {
+            Ok(()) => Ok(true),
+            Err(error) if error.raw_os_error() == Some(libc::EINVAL) => {
+                eprintln!(
+                    "Warning: cannot page out collections ({}), only \
+                     evicting caches",
+                    error
+                );
+                Ok(false)
+            },
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn record<F, T>(
+        &mut self,
+        collection: &'static str,
+        oper_name: &str,
+        size: usize,
+        operation: F,
+    ) -> io::Result<T>
+    where
+        F: FnOnce() -> T,
+    {
+        if self.reuse_writer.is_some() {
+            trace::start(ReuseProfiler::new(self.page_size));
+        }
+
+        let (minor_before, major_before) = page_faults();
+        let wall_start = self.wall_clock.now();
+        let cpu_start = self.cpu_clock.now();
+        if self.records_allocations() {
+            ALLOCATOR.start();
+        }
+        let start = self.clock.now();
+        let output = operation();
+        let elapsed = self.clock.elapsed(start);
+        let allocation_stats =
+            self.records_allocations().then(|| ALLOCATOR.stop());
+        let cpu_elapsed = self.cpu_clock.elapsed(cpu_start);
+        let wall_elapsed = self.wall_clock.elapsed(wall_start);
+        let (minor_after, major_after) = page_faults();
+
+        let row = RecordRow {
+            mode: self.mode_name,
+            size,
+            operation: oper_name,
+            collection,
+            nanoseconds: u128::from(elapsed),
+            page_size: self.page_size,
+            cache_state: self.cache_state.map(CacheState::name),
+        };
+        self.csv_writer.serialize(row)?;
+
+        if let Some(times_writer) = &mut self.times_writer {
+            let row = TimesRow {
+                mode: self.mode_name,
+                size,
+                operation: oper_name,
+                collection,
+                wall_nanoseconds: wall_elapsed,
+                cpu_nanoseconds: cpu_elapsed,
+            };
+            times_writer.serialize(row)?;
+        }
+
+        if let Some(faults_writer) = &mut self.faults_writer {
+            let row = FaultsRow {
+                mode: self.mode_name,
+                size,
+                operation: oper_name,
+                collection,
+                minor_faults: minor_after - minor_before,
+                major_faults: major_after - major_before,
+            };
+            faults_writer.serialize(row)?;
+        }
+
+        if let Some(stats) = allocation_stats {
+            self.write_allocations(collection, oper_name, size, &stats)?;
+        }
+
+        if let Some(profiler) = trace::stop() {
+            self.write_reuse(collection, oper_name, size, &profiler)?;
+        }
+
+        Ok(output)
+    }
+
+    // Runs the operation in a forked child, so that it writes to copy on
+    // write pages shared with this process.
+    pub fn record_fork<F>(
+        &mut self,
+        collection: &'static str,
+        oper_name: &str,
+        size: usize,
+        operation: F,
+    ) -> io::Result<()>
+    where
+        F: FnOnce(),
+    {
+        // Buffered rows would otherwise be duplicated in the child.
+        self.flush()?;
+
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } < 0 {
+            Err(io::Error::last_os_error())?;
+        }
+        let mut reader = unsafe { fs::File::from_raw_fd(fds[0]) };
+        let mut writer = unsafe { fs::File::from_raw_fd(fds[1]) };
+
+        let rss_before = residency::resident_set_size()?;
+        let pid = unsafe { libc::fork() };
+        if pid < 0 {
+            Err(io::Error::last_os_error())?;
+        }
+
+        if pid == 0 {
+            drop(reader);
+            // Unwinding would return into the child's copy of the parent
+            // stack, which would go on running the remaining benchmarks.
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                let (minor_before, major_before) = page_faults();
+                let start = self.clock.now();
+                operation();
+                let elapsed = self.clock.elapsed(start);
+                let (minor_after, major_after) = page_faults();
+
+                let report = [
+                    elapsed,
+                    (minor_after - minor_before) as u64,
+                    (major_after - major_before) as u64,
+                ];
+                let mut bytes =
+                    Vec::with_capacity(mem::size_of_val(&report));
+                for word in report {
+                    bytes.extend_from_slice(&word.to_ne_bytes());
+                }
+                writer.write_all(&bytes)
+            }));
+            let status = match result {
+                Ok(Ok(())) => 0,
+                Ok(Err(_)) | Err(_) => 1,
+            };
+            unsafe { libc::_exit(status) }
+        }
+
+        drop(writer);
+        let mut bytes = [0; 3 * mem::size_of::<u64>()];
+        let read_result = reader.read_exact(&mut bytes);
+        let mut status = 0;
+        if unsafe { libc::waitpid(pid, &mut status, 0) } < 0 {
+            Err(io::Error::last_os_error())?;
+        }
+        // The child panicked, failed to write or was killed, as by the OOM
+        // killer under a memory limit.
+        let failure = if libc::WIFSIGNALED(status) {
+            Some(format!("signal {}", libc::WTERMSIG(status)))
+        } else if libc::WEXITSTATUS(status) != 0 {
+            Some(format!("exit code {}", libc::WEXITSTATUS(status)))
+        } else {
+            read_result.err().map(|error| format!("no report ({})", error))
+        };
+        if let Some(failure) = failure {
+            return self.record_failure(collection, oper_name, size, &failure);
+        }
+        let rss_after = residency::resident_set_size()?;
+
+        let mut report = [0u64; 3];
+        for (word, chunk) in
+            report.iter_mut().zip(bytes.chunks(mem::size_of::<u64>()))
+        {
+            *word = u64::from_ne_bytes(chunk.try_into().unwrap());
+        }
+
+        let record_row = RecordRow {
+            mode: self.mode_name,
+            size,
+            operation: oper_name,
+            collection,
+            nanoseconds: u128::from(report[0]),
+            page_size: self.page_size,
+            cache_state: self.cache_state.map(CacheState::name),
+        };
+        self.csv_writer.serialize(record_row)?;
+
+        if let Some(fork_writer) = &mut self.fork_writer {
+            let row = ForkRow {
+                mode: self.mode_name,
+                size,
+                operation: oper_name,
+                collection,
+                nanoseconds: report[0],
+                minor_faults: report[1] as i64,
+                major_faults: report[2] as i64,
+                parent_rss_growth: rss_after as i64 - rss_before as i64,
+            };
+            fork_writer.serialize(row)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn record_failure(
+        &mut self,
+        collection: &str,
+        oper_name: &str,
+        size: usize,
+        failure: &str,
+    ) -> io::Result<()> {
+        let row = FailedRow {
+            mode: self.mode_name,
+            size,
+            operation: oper_name,
+            collection,
+            nanoseconds: None,
+            page_size: self.page_size,
+            cache_state: self.cache_state.map(CacheState::name),
+            failure,
+        };
+        self.csv_writer.serialize(row)?;
+        Ok(())
+    }
+
+    // Copies rows written by a child process.
+    pub fn pass_through(&mut self, rows: &[u8]) -> io::Result<()> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(rows);
+        for row in reader.byte_records() {
+            self.csv_writer.write_byte_record(&row?)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.csv_writer.flush()?;
+        let writers = [
+            &mut self.reuse_writer,
+            &mut self.residency_writer,
+            &mut self.faults_writer,
+            &mut self.fork_writer,
+            &mut self.times_writer,
+            &mut self.footprint_writer,
+            &mut self.height_writer,
+            &mut self.allocations_writer,
+            &mut self.allocation_sizes_writer,
+        ];
+        for writer in writers.into_iter().flatten() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn record_residency<C, E>(
+        &mut self,
+        collection: &C,
+        oper_name: &str,
+        size: usize,
+    ) -> io::Result<()>
+    where
+        C: Collection<E>,
+        E: Element,
+    {
+        let residency_writer = match &mut self.residency_writer {
+            Some(residency_writer) => residency_writer,
+            None => return Ok(()),
+        };
+
+        let mut pages = PageSet::new(residency::system_page_size());
+        collection.storage(&mut pages);
+        let row = ResidencyRow {
+            mode: self.mode_name,
+            size,
+            operation: oper_name,
+            collection: C::NAME,
+            pages: pages.pages(),
+            resident_pages: pages.resident()?,
+        };
+        residency_writer.serialize(row)?;
+
+        Ok(())
+    }
+
+    // Payload is the size of the input, and overhead everything else
+    // allocated by the collection.
+    pub fn record_footprint<C, E>(
+        &mut self,
+        collection: &C,
+        oper_name: &str,
+        size: usize,
+        rss_growth: i64,
+    ) -> io::Result<()>
+    where
+        C: Collection<E>,
+        E: Element,
+    {
+        let footprint_writer = match &mut self.footprint_writer {
+            Some(footprint_writer) => footprint_writer,
+            None => return Ok(()),
+        };
+
+        let mut pages = PageSet::new(residency::system_page_size());
+        collection.storage(&mut pages);
+        let payload_bytes = collection.element_count() * mem::size_of::<E>();
+        let row = FootprintRow {
+            mode: self.mode_name,
+            size,
+            operation: oper_name,
+            collection: C::NAME,
+            payload_bytes,
+            overhead_bytes: collection
+                .allocated_bytes()
+                .saturating_sub(payload_bytes),
+            rss_growth,
+            pages: pages.pages(),
+        };
+        footprint_writer.serialize(row)?;
+
+        Ok(())
+    }
+
+    pub fn record_height<C, E>(
+        &mut self,
+        collection: &C,
+        size: usize,
+    ) -> io::Result<()>
+    where
+        C: Collection<E>,
+        E: Element,
+    {
+        let (height_writer, height) =
+            match (&mut self.height_writer, collection.height()) {
+                (Some(height_writer), Some(height)) => (height_writer, height),
+                _ => return Ok(()),
+            };
+        let row = HeightRow {
+            mode: self.mode_name,
+            size,
+            collection: C::NAME,
+            height,
+        };
+        height_writer.serialize(row)?;
+
+        Ok(())
+    }
+
+    fn write_allocations(
+        &mut self,
+        collection: &'static str,
+        oper_name: &str,
+        size: usize,
+        stats: &AllocationStats,
+    ) -> io::Result<()> {
+        if let Some(allocations_writer) = &mut self.allocations_writer {
+            let row = AllocationsRow {
+                mode: self.mode_name,
+                size,
+                operation: oper_name,
+                collection,
+                allocations: stats.allocations,
+                reallocations: stats.reallocations,
+                frees: stats.frees,
+                allocated_bytes: stats.allocated_bytes,
+                freed_bytes: stats.freed_bytes,
+                peak_live_bytes: stats.peak_live_bytes,
+            };
+            allocations_writer.serialize(row)?;
+        }
+
+        if let Some(sizes_writer) = &mut self.allocation_sizes_writer {
+            for class in stats.size_classes() {
+                let row = AllocationSizeRow {
+                    mode: self.mode_name,
+                    size,
+                    operation: oper_name,
+                    collection,
+                    size_class: class.size,
+                    count: class.count,
+                    frees: class.frees,
+                    mean_lifetime: class.mean_lifetime,
+                };
+                sizes_writer.serialize(row)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_reuse(
+        &mut self,
+        collection: &'static str,
+        oper_name: &str,
+        size: usize,
+        profiler: &ReuseProfiler,
+    ) -> io::Result<()> {
+        let reuse_writer = match &mut self.reuse_writer {
+            Some(reuse_writer) => reuse_writer,
+            None => return Ok(()),
+        };
+
+        for stack_distance in profiler.granularities() {
+            let histogram = stack_distance.histogram();
+            let cold_row = ReuseRow {
+                mode: self.mode_name,
+                size,
+                operation: oper_name,
+                collection,
+                granularity: stack_distance.granularity(),
+                distance: None,
+                count: histogram.cold(),
+            };
+            reuse_writer.serialize(cold_row)?;
+
+            for (distance, count) in histogram.buckets() {
+                let row = ReuseRow {
+                    distance: Some(distance),
+                    count,
+                    ..cold_row
+                };
+                reuse_writer.serialize(row)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{
+    collection::{self, Collection},
+    config::Config,
+    element::Element,
+    record::Recorder,
+};
+use std::io;
+
+pub type NewRunner<E, W> = fn() -> Box<dyn Runner<E, W>>;
+
+// Every benchmarked collection, in the order rows are written. Adding a
+// collection only takes adding it here.
+pub fn registry<E, W>() -> Vec<(&'static str, NewRunner<E, W>)>
+where
+    E: Element,
+    W: io::Write,
+{
+    vec![
+        entry::<collection::GoodLocalArray<E>, E, W>(),
+        entry::<collection::BadLocalArray<E>, E, W>(),
+        entry::<collection::WorseLocalArray<E>, E, W>(),
+        entry::<collection::SortedArray<E>, E, W>(),
+        entry::<collection::LinkedList<E>, E, W>(),
+        entry::<collection::WithOrderTree<E>, E, W>(),
+        entry::<collection::WithoutOrderTree<E>, E, W>(),
+        entry::<collection::ArenaWithOrderTree<E>, E, W>(),
+        entry::<collection::ArenaWithoutOrderTree<E>, E, W>(),
+        entry::<collection::BalancedTree<E>, E, W>(),
+        entry::<collection::BTree<E>, E, W>(),
+        entry::<collection::EytzingerArray<E>, E, W>(),
+        entry::<collection::VebArray<E>, E, W>(),
+        entry::<collection::OpenTable<E>, E, W>(),
+        entry::<collection::ChainedTable<E>, E, W>(),
+        entry::<collection::SkipList<E>, E, W>(),
+        entry::<collection::UnrolledList<E>, E, W>(),
+        entry::<collection::MoveToFrontList<E>, E, W>(),
+        entry::<collection::TransposeList<E>, E, W>(),
+        entry::<collection::DoublyLinkedList<E>, E, W>(),
+        entry::<collection::XorLinkedList<E>, E, W>(),
+        entry::<collection::FenwickArray<E>, E, W>(),
+    ]
+}
+
+fn entry<C, E, W>() -> (&'static str, NewRunner<E, W>)
+where
+    C: Collection<E> + 'static,
+    E: Element,
+    W: io::Write,
+{
+    (C::NAME, || Box::new(CollectionRunner::<C> { collection: None }))
+}
+
+// What the operations of every collection run with, for one input size.
+#[derive(Debug, Clone)]
+pub struct Workload<'input, E>
+where
+    E: Element,
+{
+    pub all_elements: &'input [E],
+    pub target_elements: [E; 4],
+    pub skewed_targets: Vec<E>,
+    pub config: &'input Config,
+    pub fork_targets: Vec<E>,
+}
+
+// Holds one collection between the operations run on it.
+pub trait Runner<E, W>
+where
+    E: Element,
+    W: io::Write,
+{
+    // Unrecorded operations still change the collection as recorded ones
+    // would, so that later operations find it in the same state. Returns
+    // whether every element was found, for finds.
+    fn run(
+        &mut self,
+        oper_name: &str,
+        recorded: bool,
+        workload: &Workload<E>,
+        recorder: &mut Recorder<W>,
+    ) -> io::Result<Option<bool>>;
+}
+
+struct CollectionRunner<C> {
+    collection: Option<C>,
+}
+
+impl<C, E, W> Runner<E, W> for CollectionRunner<C>
+where
+    C: Collection<E>,
+    E: Element,
+    W: io::Write,
+{
+    fn run(
+        &mut self,
+        oper_name: &str,
+        recorded: bool,
+        workload: &Workload<E>,
+        recorder: &mut Recorder<W>,
+    ) -> io::Result<Option<bool>> {
+        let all_elements = workload.all_elements;
+        if oper_name == "create" {
+            let collection = if recorded {
+                C::record_create(
+                    all_elements,
+                    workload.config,
+                    oper_name,
+                    recorder,
+                )?
+            } else {
+                C::create(all_elements, workload.config)?
+            };
+            self.collection = Some(collection);
+            return Ok(None);
+        }
+
+        let collection = match &mut self.collection {
+            Some(collection) => collection,
+            None => panic!("{} runs before create", oper_name),
+        };
+        let targets = match oper_name {
+            "inc-less-than" => {
+                if recorded {
+                    collection.record_inc_less_than(
+                        &workload.target_elements,
+                        all_elements,
+                        oper_name,
+                        recorder,
+                    )?;
+                } else {
+                    for &element in &workload.target_elements {
+                        collection.inc_less_than(element);
+                    }
+                }
+                return Ok(None);
+            },
+            "find" => &workload.target_elements[..],
+            "skewed-find" => &workload.skewed_targets[..],
+            // Neither changes the collection.
+            "clone" | "fork-cow" if !recorded => return Ok(None),
+            "clone" => {
+                collection.record_clone(all_elements, oper_name, recorder)?;
+                return Ok(None);
+            },
+            "fork-cow" => {
+                collection.record_fork_cow(
+                    &workload.fork_targets,
+                    all_elements,
+                    oper_name,
+                    recorder,
+                )?;
+                return Ok(None);
+            },
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown operation {}", oper_name),
+            ))?,
+        };
+
+        if recorded {
+            let found_all = collection.record_find(
+                targets,
+                all_elements,
+                oper_name,
+                recorder,
+            )?;
+            Ok(Some(found_all))
+        } else {
+            for &element in targets {
+                collection.find(element);
+            }
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::{fs, io, mem};
+
+pub fn system_page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+pub fn resident_set_size() -> io::Result<usize> {
+    let statm = fs::read_to_string("/proc/self/statm")?;
+    let resident_pages: usize = statm
+        .split_whitespace()
+        .nth(1)
+        .and_then(|field| field.parse().ok())
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "malformed statm")
+        })?;
+    Ok(resident_pages * system_page_size())
+}
+
+#[derive(Debug, Clone)]
+pub struct PageSet {
+    page_size: usize,
+    ranges: Vec<(usize, usize)>,
+}
+
+impl PageSet {
+    pub fn new(page_size: usize) -> Self {
+        Self { page_size, ranges: Vec::new() }
+    }
+
+    pub fn insert<T>(&mut self, value: &T) {
+        self.insert_region(value as *const T as usize, mem::size_of::<T>());
+    }
+
+    pub fn insert_region(&mut self, address: usize, length: usize) {
+        if length == 0 {
+            return;
+        }
+        let start = address / self.page_size;
+        let end = (address + length - 1) / self.page_size + 1;
+
+        // Nodes are usually allocated close to the previous one, so merging
+        // with the last range here keeps the set small before normalizing.
+        if let Some(last) = self.ranges.last_mut() {
+            if start <= last.1 && end >= last.0 {
+                last.0 = last.0.min(start);
+                last.1 = last.1.max(end);
+                return;
+            }
+        }
+        self.ranges.push((start, end));
+    }
+
+    fn normalize(&mut self) {
+        self.ranges.sort_unstable();
+        let mut merged: Vec<(usize, usize)> = Vec::new();
+        for &(start, end) in &self.ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        self.ranges = merged;
+    }
+
+    pub fn pages(&mut self) -> usize {
+        self.normalize();
+        self.ranges.iter().map(|(start, end)| end - start).sum()
+    }
+
+    // Page aligned address and length in bytes of every range of pages.
+    pub fn regions(&mut self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.normalize();
+        self.ranges.iter().map(|&(start, end)| {
+            (start * self.page_size, (end - start) * self.page_size)
+        })
+    }
+
+    pub fn resident(&mut self) -> io::Result<usize> {
+        self.normalize();
+        let mut residency = Vec::new();
+        let mut resident = 0;
+
+        for &(start, end) in &self.ranges {
+            residency.clear();
+            residency.resize(end - start, 0u8);
+            let status = unsafe {
+                libc::mincore(
+                    (start * self.page_size) as *mut libc::c_void,
+                    (end - start) * self.page_size,
+                    residency.as_mut_ptr(),
+                )
+            };
+            if status < 0 {
+                Err(io::Error::last_os_error())?;
+            }
+            resident += residency.iter().filter(|&&page| page & 1 != 0).count();
+        }
+
+        Ok(resident)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{system_page_size, PageSet};
+
+    #[test]
+    fn pages() {
+        let mut pages = PageSet::new(0x1000);
+        pages.insert_region(0x1ff8, 16);
+        pages.insert_region(0x8000, 0x1000);
+        pages.insert_region(0x2000, 8);
+        pages.insert_region(0x5000, 0);
+        assert_eq!(pages.pages(), 3);
+    }
+
+    #[test]
+    fn resident() {
+        let page_size = system_page_size();
+        let buffer = vec![1u8; page_size * 8];
+        let mut pages = PageSet::new(page_size);
+        pages.insert_region(buffer.as_ptr() as usize, buffer.len());
+        let total = pages.pages();
+        assert!(total >= 8);
+        assert!(pages.resident().unwrap() >= 8);
+    }
+}
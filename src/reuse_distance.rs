@@ -0,0 +1,194 @@
+use crate::fenwick::FenwickTree;
+use std::collections::HashMap;
+
+pub const CACHE_LINE_SIZE: usize = 64;
+
+const MIN_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    cold: u64,
+    buckets: Vec<u64>,
+}
+
+impl Histogram {
+    pub fn empty() -> Self {
+        Self { cold: 0, buckets: Vec::new() }
+    }
+
+    pub fn add_cold(&mut self) {
+        self.cold += 1;
+    }
+
+    // Bucket 0 holds distance 0 and bucket `k > 0` holds distances in
+    // `[2^(k - 1), 2^k)`, so a fully-associative LRU cache with `2^k` blocks
+    // misses exactly the cold accesses and buckets `k + 1` onwards.
+    pub fn add(&mut self, distance: usize) {
+        let bucket = (usize::BITS - distance.leading_zeros()) as usize;
+        if bucket >= self.buckets.len() {
+            self.buckets.resize(bucket + 1, 0);
+        }
+        self.buckets[bucket] += 1;
+    }
+
+    pub fn cold(&self) -> u64 {
+        self.cold
+    }
+
+    pub fn buckets(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.buckets
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(bucket, &count)| (bucket_lower_bound(bucket), count))
+    }
+}
+
+fn bucket_lower_bound(bucket: usize) -> usize {
+    if bucket == 0 {
+        0
+    } else {
+        1 << (bucket - 1)
+    }
+}
+
+// Computes LRU stack distances with the classic "last access time" technique:
+// each block is marked at the time of its latest access, and the distance of
+// a reuse is the number of marks after the previous access of the block.
+#[derive(Debug, Clone)]
+pub struct StackDistance {
+    granularity: usize,
+    time: usize,
+    last_access: HashMap<usize, usize>,
+    marks: FenwickTree,
+    histogram: Histogram,
+}
+
+impl StackDistance {
+    pub fn new(granularity: usize) -> Self {
+        Self {
+            granularity,
+            time: 0,
+            last_access: HashMap::new(),
+            marks: FenwickTree::new(MIN_CAPACITY),
+            histogram: Histogram::empty(),
+        }
+    }
+
+    pub fn granularity(&self) -> usize {
+        self.granularity
+    }
+
+    pub fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    pub fn access(&mut self, address: usize) {
+        if self.time == self.marks.len() {
+            self.compact();
+        }
+
+        let block = address / self.granularity;
+        match self.last_access.insert(block, self.time) {
+            Some(previous) => {
+                let distance = self.marks.prefix_sum(self.time)
+                    - self.marks.prefix_sum(previous + 1);
+                self.marks.add(previous, -1);
+                self.histogram.add(distance as usize);
+            },
+            None => self.histogram.add_cold(),
+        }
+
+        self.marks.add(self.time, 1);
+        self.time += 1;
+    }
+
+    fn compact(&mut self) {
+        let mut by_time: Vec<_> = self
+            .last_access
+            .iter()
+            .map(|(&block, &time)| (time, block))
+            .collect();
+        by_time.sort_unstable();
+
+        self.marks = FenwickTree::new(MIN_CAPACITY.max(by_time.len() * 2));
+        for (new_time, (_, block)) in by_time.into_iter().enumerate() {
+            self.last_access.insert(block, new_time);
+            self.marks.add(new_time, 1);
+        }
+        self.time = self.last_access.len();
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReuseProfiler {
+    cache_lines: StackDistance,
+    pages: StackDistance,
+}
+
+impl ReuseProfiler {
+    pub fn new(page_size: usize) -> Self {
+        Self {
+            cache_lines: StackDistance::new(CACHE_LINE_SIZE),
+            pages: StackDistance::new(page_size),
+        }
+    }
+
+    // Only traced accesses of the trace-reuse feature reach the profiler.
+    #[cfg_attr(not(feature = "trace-reuse"), allow(dead_code))]
+    pub fn access(&mut self, address: usize) {
+        self.cache_lines.access(address);
+        self.pages.access(address);
+    }
+
+    pub fn granularities(&self) -> [&StackDistance; 2] {
+        [&self.cache_lines, &self.pages]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::StackDistance;
+
+    #[test]
+    fn distances() {
+        let mut profiler = StackDistance::new(1);
+        for &address in &[1, 2, 3, 1, 1, 3, 2] {
+            profiler.access(address);
+        }
+
+        let histogram = profiler.histogram();
+        assert_eq!(histogram.cold(), 3);
+        let buckets: Vec<_> = histogram.buckets().collect();
+        assert_eq!(buckets, &[(0, 1), (1, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn granularity() {
+        let mut profiler = StackDistance::new(64);
+        for address in 0 .. 256 {
+            profiler.access(address);
+        }
+
+        let histogram = profiler.histogram();
+        assert_eq!(histogram.cold(), 4);
+        let buckets: Vec<_> = histogram.buckets().collect();
+        assert_eq!(buckets, &[(0, 252)]);
+    }
+
+    #[test]
+    fn compaction() {
+        let blocks = 3000;
+        let mut profiler = StackDistance::new(1);
+        for _ in 0 .. 4 {
+            for address in 0 .. blocks {
+                profiler.access(address);
+            }
+        }
+
+        let histogram = profiler.histogram();
+        assert_eq!(histogram.cold(), blocks as u64);
+        let buckets: Vec<_> = histogram.buckets().collect();
+        assert_eq!(buckets, &[(2048, blocks as u64 * 3)]);
+    }
+}
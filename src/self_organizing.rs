@@ -0,0 +1,241 @@
+use crate::{
+    element::Element,
+    pool::{NodeBox, Pool},
+    residency::PageSet,
+    trace,
+};
+use std::{alloc::Layout, mem};
+
+type Link<E> = Option<NodeBox<Node<E>>>;
+
+// How a list reorders itself when it finds an element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heuristic {
+    // Relinks the node found at the top.
+    MoveToFront,
+    // Swaps the element found with the one before it.
+    Transpose,
+}
+
+// Unordered linked list, like `LinkedList`, whose order adapts to lookups.
+#[derive(Debug)]
+pub struct SelfOrganizingList<E> {
+    top: Link<E>,
+    heuristic: Heuristic,
+}
+
+impl<E> SelfOrganizingList<E>
+where
+    E: Element,
+{
+    pub fn empty(heuristic: Heuristic) -> Self {
+        Self { top: None, heuristic }
+    }
+
+    pub fn node_layout() -> Layout {
+        Layout::new::<Node<E>>()
+    }
+
+    pub fn prepend(&mut self, element: E) {
+        self.prepend_with(element, NodeBox::new);
+    }
+
+    // The pool must outlive the list.
+    pub unsafe fn prepend_in(&mut self, element: E, pool: &mut Pool) {
+        self.prepend_with(element, |node| pool.alloc(node));
+    }
+
+    fn prepend_with<F>(&mut self, element: E, alloc: F)
+    where
+        F: FnOnce(Node<E>) -> NodeBox<Node<E>>,
+    {
+        let next = self.top.take();
+        let top = self.top.insert(alloc(Node { data: element, next }));
+        trace::touch(&**top);
+    }
+
+    pub fn find(&mut self, element: E) -> bool {
+        match self.heuristic {
+            Heuristic::MoveToFront => self.find_move_to_front(element),
+            Heuristic::Transpose => self.find_transpose(element),
+        }
+    }
+
+    fn find_move_to_front(&mut self, element: E) -> bool {
+        let mut link = &mut self.top;
+        loop {
+            match link {
+                Some(node) => {
+                    trace::touch(&**node);
+                    if node.data == element {
+                        break;
+                    }
+                },
+                None => return false,
+            }
+            link = &mut link.as_mut().expect("link is not empty").next;
+        }
+        let mut node = link.take().expect("link is not empty");
+        *link = node.next.take();
+        node.next = self.top.take();
+        self.top = Some(node);
+        true
+    }
+
+    fn find_transpose(&mut self, element: E) -> bool {
+        let mut node = match &mut self.top {
+            Some(top) => top,
+            None => return false,
+        };
+        trace::touch(&**node);
+        if node.data == element {
+            return true;
+        }
+        loop {
+            let Node { data, next } = &mut **node;
+            let next = match next {
+                Some(next) => next,
+                None => return false,
+            };
+            trace::touch(&**next);
+            if next.data == element {
+                mem::swap(data, &mut next.data);
+                return true;
+            }
+            node = next;
+        }
+    }
+
+    pub fn inc_less_than(&mut self, element: E) {
+        let mut link = &mut self.top;
+        while let Some(node) = link {
+            trace::touch(&**node);
+            if node.data < element {
+                node.data = node.data.wrapping_inc();
+            }
+            link = &mut node.next;
+        }
+    }
+
+    pub fn storage(&self, pages: &mut PageSet) {
+        let mut link = &self.top;
+        while let Some(node) = link {
+            pages.insert(&**node);
+            link = &node.next;
+        }
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        let mut link = &self.top;
+        let mut bytes = 0;
+        while let Some(node) = link {
+            bytes += node.allocated_size();
+            link = &node.next;
+        }
+        bytes
+    }
+}
+
+impl<E> Clone for SelfOrganizingList<E>
+where
+    E: Element,
+{
+    fn clone(&self) -> Self {
+        let mut new_list = Self::empty(self.heuristic);
+        let mut new_end = &mut new_list.top;
+        let mut link = &self.top;
+        while let Some(node) = link {
+            let new_node = new_end.insert(NodeBox::new(Node {
+                data: node.data,
+                next: None,
+            }));
+            new_end = &mut new_node.next;
+            link = &node.next;
+        }
+        new_list
+    }
+}
+
+impl<E> Drop for SelfOrganizingList<E> {
+    fn drop(&mut self) {
+        let mut link = self.top.take();
+        while let Some(mut node) = link {
+            link = node.next.take();
+        }
+    }
+}
+
+impl<'list, E> IntoIterator for &'list SelfOrganizingList<E>
+where
+    E: Element,
+{
+    type Item = E;
+    type IntoIter = Iter<'list, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter { link: &self.top }
+    }
+}
+
+#[derive(Debug)]
+pub struct Iter<'list, E> {
+    link: &'list Link<E>,
+}
+
+impl<'list, E> Iterator for Iter<'list, E>
+where
+    E: Element,
+{
+    type Item = E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.link.as_ref()?;
+        self.link = &node.next;
+        Some(node.data)
+    }
+}
+
+#[derive(Debug)]
+struct Node<E> {
+    data: E,
+    next: Link<E>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Heuristic, SelfOrganizingList};
+
+    fn list(heuristic: Heuristic) -> SelfOrganizingList<u64> {
+        let mut list = SelfOrganizingList::empty(heuristic);
+        for element in [10, 3, 5, 9] {
+            list.prepend(element);
+        }
+        list
+    }
+
+    #[test]
+    fn move_to_front() {
+        let mut list = list(Heuristic::MoveToFront);
+        assert!(list.find(3));
+        assert!(list.find(10));
+        assert!(!list.find(4));
+        assert!(list.find(10));
+        assert_eq!(list.into_iter().collect::<Vec<_>>(), [10, 3, 9, 5]);
+        list.inc_less_than(9);
+        let cloned = list.clone();
+        assert_eq!(cloned.into_iter().collect::<Vec<_>>(), [10, 4, 9, 6]);
+    }
+
+    #[test]
+    fn transpose() {
+        let mut list = list(Heuristic::Transpose);
+        assert!(list.find(10));
+        assert!(list.find(10));
+        assert!(!list.find(4));
+        assert!(list.find(9));
+        assert_eq!(list.into_iter().collect::<Vec<_>>(), [9, 10, 5, 3]);
+        list.inc_less_than(9);
+        let cloned = list.clone();
+        assert_eq!(cloned.into_iter().collect::<Vec<_>>(), [9, 10, 6, 4]);
+    }
+}
@@ -0,0 +1,291 @@
+use crate::{
+    element::Element,
+    pool::{NodeBox, NodePtr, Pool},
+    residency::PageSet,
+    trace,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{alloc::Layout, cmp, marker::PhantomData, mem};
+
+const MAX_LEVELS: usize = 32;
+
+type Link<E> = Option<NodePtr<Node<E>>>;
+
+// Only the node is taken from a pool, its links varying in number.
+#[derive(Debug)]
+struct Node<E> {
+    data: E,
+    // One link per level the node is in.
+    next: Box<[Link<E>]>,
+}
+
+impl<E> Node<E> {
+    fn new(data: E, height: usize) -> Self {
+        Self { data, next: vec![None; height].into_boxed_slice() }
+    }
+}
+
+// Skip list keeping duplicates, like a sorted array. Every node is also in
+// the level above with probability 1/2. The head is boxed so that links to
+// it stay valid when the list moves.
+#[derive(Debug)]
+pub struct SkipList<E> {
+    head: Box<[Link<E>]>,
+    rng: StdRng,
+}
+
+impl<E> SkipList<E>
+where
+    E: Element,
+{
+    pub fn new(seed: [u8; 32]) -> Self {
+        Self {
+            head: vec![None; MAX_LEVELS].into_boxed_slice(),
+            rng: StdRng::from_seed(seed),
+        }
+    }
+
+    // Levels with any node.
+    pub fn height(&self) -> usize {
+        self.head.iter().take_while(|link| link.is_some()).count()
+    }
+
+    // The link at every level to the first node not less than `element`.
+    fn links_before(&mut self, element: E) -> [*mut Link<E>; MAX_LEVELS] {
+        let mut links = [std::ptr::null_mut(); MAX_LEVELS];
+        let mut next: *mut [Link<E>] = &mut *self.head;
+        for level in (0 .. MAX_LEVELS).rev() {
+            unsafe {
+                while let Some(node) = (*next)[level] {
+                    trace::touch(node.as_ref());
+                    if node.as_ref().data >= element {
+                        break;
+                    }
+                    next = &mut *(*node.as_ptr()).next;
+                }
+                links[level] = &mut (*next)[level];
+            }
+        }
+        links
+    }
+
+    pub fn node_layout() -> Layout {
+        Layout::new::<Node<E>>()
+    }
+
+    pub fn insert(&mut self, element: E) {
+        self.insert_with(element, NodeBox::new);
+    }
+
+    // The pool must outlive the list.
+    pub unsafe fn insert_in(&mut self, element: E, pool: &mut Pool) {
+        self.insert_with(element, |node| pool.alloc(node));
+    }
+
+    fn insert_with<F>(&mut self, element: E, alloc: F)
+    where
+        F: FnOnce(Node<E>) -> NodeBox<Node<E>>,
+    {
+        let mut height = 1;
+        while height < MAX_LEVELS && self.rng.gen::<bool>() {
+            height += 1;
+        }
+        let links = self.links_before(element);
+        let node = alloc(Node::new(element, height)).into_node_ptr();
+        for (level, &link) in links.iter().enumerate().take(height) {
+            unsafe {
+                (*node.as_ptr()).next[level] = *link;
+                *link = Some(node);
+            }
+        }
+        trace::touch(unsafe { node.as_ref() });
+    }
+
+    // Removes one copy of the element.
+    pub fn remove(&mut self, element: E) -> bool {
+        let links = self.links_before(element);
+        let node = match unsafe { *links[0] } {
+            Some(node) if unsafe { node.as_ref() }.data == element => node,
+            _ => return false,
+        };
+        // Links at the levels of the node all point to it, since it is the
+        // first not less than the element.
+        let node = unsafe { node.into_box() };
+        for (&link, &next) in links.iter().zip(node.next.iter()) {
+            unsafe { *link = next };
+        }
+        true
+    }
+
+    pub fn find(&self, element: E) -> bool {
+        let mut next: &[Link<E>] = &self.head;
+        for level in (0 .. MAX_LEVELS).rev() {
+            while let Some(node) = next[level] {
+                let node = unsafe { node.as_ref() };
+                trace::touch(node);
+                match node.data.cmp(&element) {
+                    cmp::Ordering::Less => next = &node.next,
+                    cmp::Ordering::Equal => return true,
+                    cmp::Ordering::Greater => break,
+                }
+            }
+        }
+        false
+    }
+
+    // Elements less than `element` come first on the bottom level, and
+    // incrementing them keeps the order since duplicates are allowed.
+    pub fn inc_less_than(&mut self, element: E) {
+        let mut link = self.head[0];
+        while let Some(node) = link {
+            let node = unsafe { &mut *node.as_ptr() };
+            trace::touch(&*node);
+            if node.data >= element {
+                break;
+            }
+            node.data = node.data.wrapping_inc();
+            link = node.next[0];
+        }
+    }
+
+    pub fn storage(&self, pages: &mut PageSet) {
+        pages.insert_region(
+            self.head.as_ptr() as usize,
+            self.head.len() * mem::size_of::<Link<E>>(),
+        );
+        let mut link = self.head[0];
+        while let Some(node) = link {
+            let node = unsafe { node.as_ref() };
+            pages.insert(node);
+            pages.insert_region(
+                node.next.as_ptr() as usize,
+                node.next.len() * mem::size_of::<Link<E>>(),
+            );
+            link = node.next[0];
+        }
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        let mut bytes = self.head.len() * mem::size_of::<Link<E>>();
+        let mut link = self.head[0];
+        while let Some(node) = link {
+            bytes += node.allocated_size();
+            let node = unsafe { node.as_ref() };
+            bytes += node.next.len() * mem::size_of::<Link<E>>();
+            link = node.next[0];
+        }
+        bytes
+    }
+}
+
+impl<E> Clone for SkipList<E>
+where
+    E: Element,
+{
+    // Nodes keep their heights.
+    fn clone(&self) -> Self {
+        let mut head = vec![None; MAX_LEVELS].into_boxed_slice();
+        let mut ends: Vec<*mut Link<E>> =
+            head.iter_mut().map(|link| link as *mut _).collect();
+        let mut link = self.head[0];
+        while let Some(node) = link {
+            let node = unsafe { node.as_ref() };
+            let height = node.next.len();
+            let new_node = NodeBox::new(Node::new(node.data, height));
+            let new_node = new_node.into_node_ptr();
+            for (level, end) in ends.iter_mut().enumerate().take(height) {
+                unsafe {
+                    **end = Some(new_node);
+                    *end = &mut (*new_node.as_ptr()).next[level];
+                }
+            }
+            link = node.next[0];
+        }
+        Self { head, rng: self.rng.clone() }
+    }
+}
+
+impl<E> Drop for SkipList<E> {
+    fn drop(&mut self) {
+        let mut link = self.head[0];
+        while let Some(node) = link {
+            let node = unsafe { node.into_box() };
+            link = node.next[0];
+        }
+    }
+}
+
+impl<'list, E> IntoIterator for &'list SkipList<E>
+where
+    E: Element,
+{
+    type Item = E;
+    type IntoIter = Iter<'list, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter { link: self.head[0], _list: PhantomData }
+    }
+}
+
+// Walks the bottom level.
+#[derive(Debug)]
+pub struct Iter<'list, E> {
+    link: Link<E>,
+    _list: PhantomData<&'list SkipList<E>>,
+}
+
+impl<'list, E> Iterator for Iter<'list, E>
+where
+    E: Element,
+{
+    type Item = E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = unsafe { self.link?.as_ref() };
+        self.link = node.next[0];
+        Some(node.data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SkipList;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn same_as_sorted() {
+        let mut rng = StdRng::seed_from_u64(23);
+        let mut list = SkipList::<u64>::new([1; 32]);
+        let mut sorted = Vec::new();
+        for _ in 0 .. 3000 {
+            let element = rng.gen_range(0 .. 1000);
+            list.insert(element);
+            sorted.push(element);
+        }
+        sorted.sort();
+        assert!(list.into_iter().eq(sorted.iter().copied()));
+        assert!(list.height() > 5 && list.height() < 25);
+
+        for _ in 0 .. 20 {
+            let target = rng.gen_range(0 .. 1100);
+            list.inc_less_than(target);
+            for element in &mut sorted {
+                *element += (*element < target) as u64;
+            }
+            let removed = rng.gen_range(0 .. 1100);
+            let index = sorted.binary_search(&removed);
+            assert_eq!(list.remove(removed), index.is_ok());
+            if let Ok(index) = index {
+                sorted.remove(index);
+            }
+            assert!(list.into_iter().eq(sorted.iter().copied()));
+            for element in 0 .. 1100 {
+                assert_eq!(
+                    list.find(element),
+                    sorted.binary_search(&element).is_ok()
+                );
+            }
+        }
+        assert!(list.clone().into_iter().eq(sorted.iter().copied()));
+    }
+}
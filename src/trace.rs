@@ -0,0 +1,66 @@
+use crate::reuse_distance::ReuseProfiler;
+#[cfg(feature = "trace-reuse")]
+use std::{
+    cell::RefCell,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+// Only traces when built with the trace-reuse feature, so that untraced
+// builds compile accesses to nothing.
+pub fn is_enabled() -> bool {
+    cfg!(feature = "trace-reuse")
+}
+
+// Checked before touching the thread-local so that runs which do not profile
+// only pay for one relaxed load per access.
+#[cfg(feature = "trace-reuse")]
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "trace-reuse")]
+thread_local! {
+    static PROFILER: RefCell<Option<ReuseProfiler>> =
+        const { RefCell::new(None) };
+}
+
+#[cfg(feature = "trace-reuse")]
+#[inline(always)]
+pub fn touch<T>(value: &T) {
+    if ENABLED.load(Ordering::Relaxed) {
+        touch_traced(value as *const T as usize);
+    }
+}
+
+#[cfg(not(feature = "trace-reuse"))]
+#[inline(always)]
+pub fn touch<T>(_value: &T) {}
+
+#[cfg(feature = "trace-reuse")]
+#[cold]
+#[inline(never)]
+fn touch_traced(address: usize) {
+    PROFILER.with(|profiler| {
+        if let Some(profiler) = profiler.borrow_mut().as_mut() {
+            profiler.access(address);
+        }
+    });
+}
+
+#[cfg(feature = "trace-reuse")]
+pub fn start(profiler: ReuseProfiler) {
+    PROFILER.with(|cell| *cell.borrow_mut() = Some(profiler));
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+#[cfg(not(feature = "trace-reuse"))]
+pub fn start(_profiler: ReuseProfiler) {}
+
+#[cfg(feature = "trace-reuse")]
+pub fn stop() -> Option<ReuseProfiler> {
+    ENABLED.store(false, Ordering::Relaxed);
+    PROFILER.with(|cell| cell.borrow_mut().take())
+}
+
+#[cfg(not(feature = "trace-reuse"))]
+pub fn stop() -> Option<ReuseProfiler> {
+    None
+}
@@ -1,49 +1,101 @@
-use crate::Element;
-use std::cmp;
+use crate::{
+    element::Element,
+    pool::{NodeBox, Pool},
+    residency::PageSet,
+    trace,
+};
+use std::{alloc::Layout, cmp};
 
 #[derive(Debug)]
-pub struct Tree {
-    root: Option<Box<Node>>,
+pub struct Tree<E> {
+    root: Option<NodeBox<Node<E>>>,
 }
 
-impl Default for Tree {
+impl<E> Default for Tree<E>
+where
+    E: Element,
+{
     fn default() -> Self {
         Self::empty()
     }
 }
 
-impl Tree {
+impl<E> Tree<E>
+where
+    E: Element,
+{
     pub fn empty() -> Self {
         Self { root: None }
     }
 
-    pub fn insert_with_order(&mut self, element: Element) -> bool {
+    pub fn node_layout() -> Layout {
+        Layout::new::<Node<E>>()
+    }
+
+    pub fn insert_with_order(&mut self, element: E) -> bool {
+        self.insert_with_order_with(element, NodeBox::new)
+    }
+
+    // The pool must outlive the tree.
+    pub unsafe fn insert_with_order_in(
+        &mut self,
+        element: E,
+        pool: &mut Pool,
+    ) -> bool {
+        self.insert_with_order_with(element, |node| pool.alloc(node))
+    }
+
+    fn insert_with_order_with<F>(&mut self, element: E, alloc: F) -> bool
+    where
+        F: FnOnce(Node<E>) -> NodeBox<Node<E>>,
+    {
         let mut this = self;
         loop {
             match &mut this.root {
-                Some(node) => match element.cmp(&node.data) {
-                    cmp::Ordering::Equal => return false,
-                    cmp::Ordering::Less => this = &mut node.left,
-                    cmp::Ordering::Greater => this = &mut node.right,
+                Some(node) => {
+                    trace::touch(&**node);
+                    match element.cmp(&node.data) {
+                        cmp::Ordering::Equal => return false,
+                        cmp::Ordering::Less => this = &mut node.left,
+                        cmp::Ordering::Greater => this = &mut node.right,
+                    }
                 },
                 root @ None => {
-                    *root = Some(Box::new(Node {
+                    let node = root.insert(alloc(Node {
                         data: element,
                         left: Tree::empty(),
                         right: Tree::empty(),
                     }));
+                    trace::touch(&**node);
                     return true;
                 },
             }
         }
     }
 
-    pub fn insert_without_order(&mut self, element: Element) {
+    pub fn insert_without_order(&mut self, element: E) {
+        self.insert_without_order_with(element, NodeBox::new)
+    }
+
+    // The pool must outlive the tree.
+    pub unsafe fn insert_without_order_in(
+        &mut self,
+        element: E,
+        pool: &mut Pool,
+    ) {
+        self.insert_without_order_with(element, |node| pool.alloc(node))
+    }
+
+    fn insert_without_order_with<F>(&mut self, element: E, alloc: F)
+    where
+        F: FnOnce(Node<E>) -> NodeBox<Node<E>>,
+    {
         let mut this = self;
         let mut reverse = false;
         loop {
             match &mut this.root {
                 Some(node) => {
+                    trace::touch(&**node);
                     this = match (element.cmp(&node.data), reverse) {
                         (cmp::Ordering::Equal, true)
                         | (cmp::Ordering::Less, false)
@@ -55,20 +107,22 @@ impl Tree {
                     reverse = !reverse;
                 },
                 root @ None => {
-                    *root = Some(Box::new(Node {
+                    let node = root.insert(alloc(Node {
                         data: element,
                         left: Tree::empty(),
                         right: Tree::empty(),
                     }));
+                    trace::touch(&**node);
                     break;
                 },
             }
         }
     }
 
-    pub fn find_with_order(&self, element: Element) -> bool {
+    pub fn find_with_order(&self, element: E) -> bool {
         let mut this = self;
         while let Some(node) = &this.root {
+            trace::touch(&**node);
             match element.cmp(&node.data) {
                 cmp::Ordering::Equal => return true,
                 cmp::Ordering::Less => this = &node.left,
@@ -78,10 +132,11 @@ impl Tree {
         false
     }
 
-    pub fn find_without_order(&self, element: Element) -> bool {
-        let mut nodes: Vec<&Option<Box<Node>>> = vec![&self.root];
+    pub fn find_without_order(&self, element: E) -> bool {
+        let mut nodes: Vec<&Option<NodeBox<Node<E>>>> = vec![&self.root];
         while let Some(maybe_node) = nodes.pop() {
             if let Some(node) = maybe_node {
+                trace::touch(&**node);
                 if node.data == element {
                     return true;
                 }
@@ -92,22 +147,64 @@ impl Tree {
         false
     }
 
+    pub fn storage(&self, pages: &mut PageSet) {
+        let mut nodes: Vec<&Option<NodeBox<Node<E>>>> = vec![&self.root];
+        while let Some(maybe_node) = nodes.pop() {
+            if let Some(node) = maybe_node {
+                pages.insert(&**node);
+                nodes.push(&node.left.root);
+                nodes.push(&node.right.root);
+            }
+        }
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        let mut nodes: Vec<&Option<NodeBox<Node<E>>>> = vec![&self.root];
+        let mut bytes = 0;
+        while let Some(maybe_node) = nodes.pop() {
+            if let Some(node) = maybe_node {
+                bytes += node.allocated_size();
+                nodes.push(&node.left.root);
+                nodes.push(&node.right.root);
+            }
+        }
+        bytes
+    }
+
+    pub fn height(&self) -> usize {
+        let mut nodes = vec![(&self.root, 1)];
+        let mut height = 0;
+        while let Some((maybe_node, depth)) = nodes.pop() {
+            if let Some(node) = maybe_node {
+                height = height.max(depth);
+                nodes.push((&node.left.root, depth + 1));
+                nodes.push((&node.right.root, depth + 1));
+            }
+        }
+        height
+    }
+
     fn inc_all(&mut self) {
         let mut nodes = vec![&mut self.root];
         while let Some(maybe_node) = nodes.pop() {
-            if let Some(node) = maybe_node.as_mut() {
-                node.data = node.data.wrapping_add(1);
+            if let Some(node) = maybe_node.as_deref_mut() {
+                trace::touch(&*node);
+                node.data = node.data.wrapping_inc();
                 nodes.push(&mut node.left.root);
                 nodes.push(&mut node.right.root);
             }
         }
     }
 
-    fn remove_duplicated_max(&mut self, parent: Element) {
+    #[allow(clippy::while_let_loop)]
+    fn remove_duplicated_max(&mut self, parent: E) {
         let mut this = self;
         loop {
             let (is_max, is_duplicated) = match this.root.as_ref() {
-                Some(node) => (node.right.root.is_none(), node.data == parent),
+                Some(node) => {
+                    trace::touch(&**node);
+                    (node.right.root.is_none(), node.data == parent)
+                },
                 None => break,
             };
             if is_max {
@@ -121,19 +218,25 @@ impl Tree {
         }
     }
 
-    pub fn inc_less_than_with_order(&mut self, element: Element) {
+    #[allow(clippy::while_let_loop)]
+    pub fn inc_less_than_with_order(&mut self, element: E) {
         if let Some(mut this_node) = self.root.as_mut() {
+            trace::touch(&**this_node);
             if this_node.data < element {
                 self.inc_all();
             } else {
                 loop {
                     let found_branch = match this_node.left.root.as_ref() {
-                        Some(node) => node.data < element,
+                        Some(node) => {
+                            trace::touch(&**node);
+                            node.data < element
+                        },
                         None => break,
                     };
                     if found_branch {
+                        let parent = this_node.data;
                         this_node.left.inc_all();
-                        this_node.left.remove_duplicated_max(this_node.data);
+                        this_node.left.remove_duplicated_max(parent);
                         break;
                     }
                     this_node = this_node.left.root.as_mut().unwrap();
@@ -142,12 +245,14 @@ impl Tree {
         }
     }
 
-    pub fn inc_less_than_without_order(&mut self, element: Element) {
-        let mut nodes: Vec<&mut Option<Box<Node>>> = vec![&mut self.root];
+    pub fn inc_less_than_without_order(&mut self, element: E) {
+        let mut nodes: Vec<&mut Option<NodeBox<Node<E>>>> =
+            vec![&mut self.root];
         while let Some(maybe_node) = nodes.pop() {
-            if let Some(node) = maybe_node.as_mut() {
+            if let Some(node) = maybe_node.as_deref_mut() {
+                trace::touch(&*node);
                 if node.data < element {
-                    node.data = node.data.wrapping_add(1);
+                    node.data = node.data.wrapping_inc();
                 }
                 nodes.push(&mut node.left.root);
                 nodes.push(&mut node.right.root);
@@ -156,19 +261,22 @@ impl Tree {
     }
 }
 
-impl Clone for Tree {
+impl<E> Clone for Tree<E>
+where
+    E: Element,
+{
     fn clone(&self) -> Self {
         let mut new_tree = Tree::empty();
         let mut nodes = vec![(self, &mut new_tree.root)];
 
         while let Some((maybe_old_node, new_node)) = nodes.pop() {
             if let Some(old_node) = &maybe_old_node.root {
-                *new_node = Some(Box::new(Node {
+                *new_node = Some(NodeBox::new(Node {
                     data: old_node.data,
                     left: Tree::empty(),
                     right: Tree::empty(),
                 }));
-                let new_node_unwrapped = new_node.as_mut().unwrap();
+                let new_node_unwrapped = new_node.as_deref_mut().unwrap();
                 nodes.push((&old_node.left, &mut new_node_unwrapped.left.root));
                 nodes.push((
                     &old_node.right,
@@ -181,9 +289,9 @@ impl Clone for Tree {
     }
 }
 
-impl Drop for Tree {
+impl<E> Drop for Tree<E> {
     fn drop(&mut self) {
-        let mut nodes: Vec<Option<Box<Node>>> = vec![self.root.take()];
+        let mut nodes: Vec<Option<NodeBox<Node<E>>>> = vec![self.root.take()];
 
         while let Some(maybe_node) = nodes.pop() {
             if let Some(mut node) = maybe_node {
@@ -194,9 +302,12 @@ impl Drop for Tree {
     }
 }
 
-impl<'tree> IntoIterator for &'tree Tree {
-    type Item = Element;
-    type IntoIter = Iter<'tree>;
+impl<'tree, E> IntoIterator for &'tree Tree<E>
+where
+    E: Element,
+{
+    type Item = E;
+    type IntoIter = Iter<'tree, E>;
 
     fn into_iter(self) -> Self::IntoIter {
         Iter {
@@ -209,18 +320,21 @@ impl<'tree> IntoIterator for &'tree Tree {
 }
 
 #[derive(Debug)]
-struct IterEntry<'tree> {
+struct IterEntry<'tree, E> {
     left_processed: bool,
-    node: &'tree Option<Box<Node>>,
+    node: &'tree Option<NodeBox<Node<E>>>,
 }
 
 #[derive(Debug)]
-pub struct Iter<'tree> {
-    entries: Vec<IterEntry<'tree>>,
+pub struct Iter<'tree, E> {
+    entries: Vec<IterEntry<'tree, E>>,
 }
 
-impl<'tree> Iterator for Iter<'tree> {
-    type Item = Element;
+impl<'tree, E> Iterator for Iter<'tree, E>
+where
+    E: Element,
+{
+    type Item = E;
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
@@ -249,20 +363,22 @@ impl<'tree> Iterator for Iter<'tree> {
 }
 
 #[derive(Debug)]
-struct Node {
-    data: Element,
-    left: Tree,
-    right: Tree,
+struct Node<E> {
+    data: E,
+    left: Tree<E>,
+    right: Tree<E>,
 }
 
 #[cfg(test)]
 mod test {
     use super::Tree;
-    use crate::{Element, ELEMS_IN_PAGE};
+    use crate::element::Element;
+
+    const ELEMS_IN_PAGE: usize = u64::ELEMS_IN_PAGE;
 
     #[test]
     fn iterate_with_order() {
-        let mut tree = Tree::empty();
+        let mut tree = Tree::<u64>::empty();
         tree.insert_with_order(10);
         tree.insert_with_order(3);
         tree.insert_with_order(5);
@@ -276,10 +392,10 @@ mod test {
 
     #[test]
     fn find() {
-        let cut_element = (ELEMS_IN_PAGE * 32 + ELEMS_IN_PAGE / 2) as Element;
+        let cut_element = (ELEMS_IN_PAGE * 32 + ELEMS_IN_PAGE / 2) as u64;
 
-        let mut tree_with_order = Tree::empty();
-        let mut tree_without_order = Tree::empty();
+        let mut tree_with_order = Tree::<u64>::empty();
+        let mut tree_without_order = Tree::<u64>::empty();
         for i in (cut_element + 1 .. cut_element * 2).step_by(2) {
             tree_with_order.insert_with_order(i + 1);
             tree_with_order.insert_with_order(i);
@@ -305,10 +421,10 @@ mod test {
 
     #[test]
     fn inc_less_than() {
-        let cut_element = (ELEMS_IN_PAGE * 4 + ELEMS_IN_PAGE / 2) as Element;
+        let cut_element = (ELEMS_IN_PAGE * 4 + ELEMS_IN_PAGE / 2) as u64;
 
-        let mut tree_with_order = Tree::empty();
-        let mut tree_without_order = Tree::empty();
+        let mut tree_with_order = Tree::<u64>::empty();
+        let mut tree_without_order = Tree::<u64>::empty();
         for i in (cut_element .. cut_element * 2).step_by(2) {
             tree_with_order.insert_with_order(i + 1);
             tree_with_order.insert_with_order(i);
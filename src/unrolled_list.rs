@@ -0,0 +1,291 @@
+use crate::{
+    element::Element,
+    pool::{self, BlockPtr, Pool},
+    residency::PageSet,
+    trace,
+};
+use std::{alloc::Layout, marker::PhantomData, mem, ptr, slice};
+
+// Linked list whose nodes hold up to a fixed number of elements. Prepending
+// fills the top node from its end, so elements keep the order of a
+// `LinkedList` built the same way.
+#[derive(Debug)]
+pub struct UnrolledList<E> {
+    top: Option<BlockPtr>,
+    layout: Layout,
+    node_capacity: usize,
+    _marker: PhantomData<E>,
+}
+
+// A node is a single block starting with its header, followed by its
+// elements. Only those from `start` on are stored, the rest being free for
+// prepending.
+#[derive(Debug)]
+#[repr(C)]
+struct Header {
+    start: usize,
+    next: Option<BlockPtr>,
+}
+
+impl<E> UnrolledList<E>
+where
+    E: Element,
+{
+    // Nodes take `node_size` bytes, or the least that holds one element.
+    pub fn new(node_size: usize) -> Self {
+        let size = node_size.max(Self::ELEMENTS_OFFSET + mem::size_of::<E>());
+        let min_align = mem::align_of::<Header>().max(mem::align_of::<E>());
+        Self {
+            top: None,
+            layout: pool::block_layout(size, min_align),
+            node_capacity: (size - Self::ELEMENTS_OFFSET) / mem::size_of::<E>(),
+            _marker: PhantomData,
+        }
+    }
+
+    const ELEMENTS_OFFSET: usize =
+        mem::size_of::<Header>().next_multiple_of(mem::align_of::<E>());
+
+    pub fn node_layout(&self) -> Layout {
+        self.layout
+    }
+
+    pub fn node_capacity(&self) -> usize {
+        self.node_capacity
+    }
+
+    pub fn prepend(&mut self, element: E) {
+        self.prepend_with(element, BlockPtr::alloc);
+    }
+
+    // The pool must outlive the list.
+    pub unsafe fn prepend_in(&mut self, element: E, pool: &mut Pool) {
+        self.prepend_with(element, |layout| pool.alloc_block(layout));
+    }
+
+    fn prepend_with<F>(&mut self, element: E, alloc: F)
+    where
+        F: FnOnce(Layout) -> BlockPtr,
+    {
+        let full = self.top.is_none_or(|top| self.header(top).start == 0);
+        if full {
+            let node = alloc(self.layout);
+            let start = self.node_capacity;
+            unsafe {
+                let header = Header { start, next: self.top };
+                (node.as_ptr() as *mut Header).write(header);
+                let elements = node.as_ptr().add(Self::ELEMENTS_OFFSET);
+                for index in 0 .. start {
+                    (elements as *mut E).add(index).write(E::MAX);
+                }
+            }
+            self.top = Some(node);
+        }
+        let top = self.top.expect("list has a top node");
+        let start = self.header(top).start - 1;
+        unsafe {
+            (*(top.as_ptr() as *mut Header)).start = start;
+            let elements = self.elements(top);
+            elements[start] = element;
+            trace::touch(&elements[start]);
+        }
+    }
+
+    fn header(&self, node: BlockPtr) -> &Header {
+        unsafe { &*(node.as_ptr() as *const Header) }
+    }
+
+    // Every element slot of the node, free ones holding `E::MAX`, to be
+    // used by one caller at a time.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn elements(&self, node: BlockPtr) -> &mut [E] {
+        let elements = node.as_ptr().add(Self::ELEMENTS_OFFSET) as *mut E;
+        slice::from_raw_parts_mut(elements, self.node_capacity)
+    }
+
+    fn stored(&self, node: BlockPtr) -> &[E] {
+        let start = self.header(node).start;
+        unsafe { &self.elements(node)[start ..] }
+    }
+
+    fn stored_mut(&mut self, node: BlockPtr) -> &mut [E] {
+        let start = self.header(node).start;
+        unsafe { &mut self.elements(node)[start ..] }
+    }
+
+    pub fn find(&self, element: E) -> bool {
+        let mut link = self.top;
+        while let Some(node) = link {
+            trace::touch(self.header(node));
+            for stored in self.stored(node) {
+                trace::touch(stored);
+                if *stored == element {
+                    return true;
+                }
+            }
+            link = self.header(node).next;
+        }
+        false
+    }
+
+    pub fn inc_less_than(&mut self, element: E) {
+        let mut link = self.top;
+        while let Some(node) = link {
+            trace::touch(self.header(node));
+            for stored in self.stored_mut(node) {
+                trace::touch(&*stored);
+                if *stored < element {
+                    *stored = stored.wrapping_inc();
+                }
+            }
+            link = self.header(node).next;
+        }
+    }
+
+    pub fn storage(&self, pages: &mut PageSet) {
+        let mut link = self.top;
+        while let Some(node) = link {
+            pages.insert_region(node.as_ptr() as usize, self.layout.size());
+            link = self.header(node).next;
+        }
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        let mut link = self.top;
+        let mut bytes = 0;
+        while let Some(node) = link {
+            bytes += node.allocated_size();
+            link = self.header(node).next;
+        }
+        bytes
+    }
+}
+
+impl<E> Clone for UnrolledList<E>
+where
+    E: Element,
+{
+    fn clone(&self) -> Self {
+        let mut new_list = Self { top: None, ..*self };
+        let mut new_end = &mut new_list.top;
+        let mut link = self.top;
+        while let Some(node) = link {
+            let new_node = BlockPtr::alloc(self.layout);
+            *new_end = Some(new_node);
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    node.as_ptr(),
+                    new_node.as_ptr(),
+                    self.layout.size(),
+                );
+                let new_header = &mut *(new_node.as_ptr() as *mut Header);
+                new_header.next = None;
+                new_end = &mut new_header.next;
+            }
+            link = self.header(node).next;
+        }
+        new_list
+    }
+}
+
+impl<E> Drop for UnrolledList<E> {
+    fn drop(&mut self) {
+        let mut link = self.top.take();
+        while let Some(node) = link {
+            unsafe {
+                link = (*(node.as_ptr() as *const Header)).next;
+                node.free(self.layout);
+            }
+        }
+    }
+}
+
+impl<'list, E> IntoIterator for &'list UnrolledList<E>
+where
+    E: Element,
+{
+    type Item = E;
+    type IntoIter = Iter<'list, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter { list: self, link: self.top, index: 0 }
+    }
+}
+
+#[derive(Debug)]
+pub struct Iter<'list, E> {
+    list: &'list UnrolledList<E>,
+    link: Option<BlockPtr>,
+    // Index into the elements stored in the current node.
+    index: usize,
+}
+
+impl<'list, E> Iterator for Iter<'list, E>
+where
+    E: Element,
+{
+    type Item = E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let node = self.link?;
+            if let Some(&element) = self.list.stored(node).get(self.index) {
+                self.index += 1;
+                return Some(element);
+            }
+            self.link = self.list.header(node).next;
+            self.index = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::UnrolledList;
+    use crate::{linked_list::LinkedList, mmap::MapOptions, pool::Pool};
+
+    #[test]
+    fn pooled_nodes() {
+        let layout = UnrolledList::<u64>::new(64).node_layout();
+        assert_eq!(layout.size(), 64);
+        let options = MapOptions::default();
+        let mut pool = Pool::new(layout, 2, &options).unwrap();
+        let mut list = UnrolledList::<u64>::new(64);
+        assert_eq!(list.node_capacity(), 6);
+        // The third node no longer fits in the pool.
+        for element in 0 .. 15 {
+            unsafe { list.prepend_in(element, &mut pool) };
+        }
+        assert!(list.into_iter().eq((0 .. 15).rev()));
+        let mut nodes = Vec::new();
+        let mut link = list.top;
+        while let Some(node) = link {
+            nodes.push(node.as_ptr() as usize);
+            link = list.header(node).next;
+        }
+        assert_eq!(nodes[1] - nodes[2], 64);
+        assert!(list.clone().into_iter().eq(&list));
+    }
+
+    #[test]
+    fn same_as_linked_list() {
+        for node_size in [8, 64, 4096] {
+            let mut unrolled = UnrolledList::<u64>::new(node_size);
+            let mut list = LinkedList::empty();
+            for i in 0 .. 3000 {
+                let element = i * 7919 % 1000;
+                unrolled.prepend(element);
+                list.prepend(element);
+            }
+            for target in [0, 500, 1001, 250] {
+                unrolled.inc_less_than(target);
+                list.inc_less_than(target);
+                assert!(unrolled.into_iter().eq(&list));
+                assert!(unrolled.clone().into_iter().eq(&list));
+                for element in 0 .. 1100 {
+                    assert_eq!(unrolled.find(element), list.find(element));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    element::Element,
+    eytzinger::{first_in_order, next_in_order},
+    residency::PageSet,
+    trace,
+};
+use std::mem;
+
+const MAX_DEPTH: usize = usize::BITS as usize;
+
+// Where the nodes at one depth of the tree are laid out. Every depth but the
+// first holds the roots of the bottom trees of one recursive split, placed
+// after the top tree of that split, whose root is at `top_depth`.
+#[derive(Debug, Clone, Copy, Default)]
+struct Level {
+    top_depth: usize,
+    top_size: usize,
+    bottom_size: usize,
+}
+
+// Sorted elements in an implicit binary search tree, indexed in breadth-first
+// order like `EytzingerArray`, but stored in van Emde Boas order: the top half
+// of the tree, then each tree hanging from it, all laid out recursively. The
+// tree is complete, so slots of the missing nodes of the last level are
+// unused.
+#[derive(Debug, Clone)]
+pub struct VebArray<E> {
+    elements: Vec<E>,
+    len: usize,
+    // Indexed by depth, the root having depth 1.
+    levels: Vec<Level>,
+}
+
+impl<E> VebArray<E>
+where
+    E: Element,
+{
+    pub fn from_sorted(sorted: &[E]) -> Self {
+        let height = (usize::BITS - sorted.len().leading_zeros()) as usize;
+        let mut levels = vec![Level::default(); height + 1];
+        split(&mut levels, 1, height);
+        let mut this = Self {
+            elements: vec![E::MAX; (1 << height) - 1],
+            len: sorted.len(),
+            levels,
+        };
+
+        let mut index = first_in_order(this.len);
+        for &element in sorted {
+            let position = this.position(index);
+            this.elements[position] = element;
+            trace::touch(&this.elements[position]);
+            index = next_in_order(index, this.len);
+        }
+        this
+    }
+
+    fn position(&self, index: usize) -> usize {
+        let depth = (usize::BITS - index.leading_zeros()) as usize;
+        let mut positions = [0; MAX_DEPTH + 1];
+        for current_depth in 2 ..= depth {
+            let ancestor = index >> (depth - current_depth);
+            positions[current_depth] =
+                self.next_position(&positions, current_depth, ancestor);
+        }
+        positions[depth]
+    }
+
+    // Position of the node with `index` at `depth`, given the positions of
+    // its ancestors.
+    fn next_position(
+        &self,
+        positions: &[usize],
+        depth: usize,
+        index: usize,
+    ) -> usize {
+        let level = self.levels[depth];
+        positions[level.top_depth]
+            + level.top_size
+            + (index & level.top_size) * level.bottom_size
+    }
+
+    // Position of the first element not less than `element`.
+    fn lower_bound(&self, element: E) -> Option<usize> {
+        let mut positions = [0; MAX_DEPTH + 1];
+        let mut bound = None;
+        let mut index = 1;
+        let mut depth = 1;
+        while index <= self.len {
+            let position = positions[depth];
+            trace::touch(&self.elements[position]);
+            if self.elements[position] < element {
+                index = 2 * index + 1;
+            } else {
+                bound = Some(position);
+                index *= 2;
+            }
+            depth += 1;
+            if index <= self.len {
+                positions[depth] = self.next_position(&positions, depth, index);
+            }
+        }
+        bound
+    }
+
+    pub fn find(&self, element: E) -> bool {
+        self.lower_bound(element)
+            .is_some_and(|position| self.elements[position] == element)
+    }
+
+    // Elements less than `element` come first in order.
+    pub fn inc_less_than(&mut self, element: E) {
+        let mut index = first_in_order(self.len);
+        while index != 0 {
+            let position = self.position(index);
+            trace::touch(&self.elements[position]);
+            if self.elements[position] >= element {
+                break;
+            }
+            self.elements[position] = self.elements[position].wrapping_inc();
+            index = next_in_order(index, self.len);
+        }
+    }
+
+    pub fn storage(&self, pages: &mut PageSet) {
+        pages.insert_region(
+            self.elements.as_ptr() as usize,
+            self.allocated_bytes(),
+        );
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        self.elements.capacity() * mem::size_of::<E>()
+            + self.levels.capacity() * mem::size_of::<Level>()
+    }
+}
+
+// Splits the tree rooted at `root_depth` with `height` levels into a top
+// tree of half the height and the bottom trees below it.
+fn split(levels: &mut [Level], root_depth: usize, height: usize) {
+    if height <= 1 {
+        return;
+    }
+    let top_height = height / 2;
+    let bottom_height = height - top_height;
+    let depth = root_depth + top_height;
+    levels[depth] = Level {
+        top_depth: root_depth,
+        top_size: (1 << top_height) - 1,
+        bottom_size: (1 << bottom_height) - 1,
+    };
+    split(levels, root_depth, top_height);
+    split(levels, depth, bottom_height);
+}
+
+impl<'array, E> IntoIterator for &'array VebArray<E>
+where
+    E: Element,
+{
+    type Item = E;
+    type IntoIter = Iter<'array, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter { array: self, index: first_in_order(self.len) }
+    }
+}
+
+#[derive(Debug)]
+pub struct Iter<'array, E> {
+    array: &'array VebArray<E>,
+    index: usize,
+}
+
+impl<'array, E> Iterator for Iter<'array, E>
+where
+    E: Element,
+{
+    type Item = E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index == 0 {
+            return None;
+        }
+        let element = self.array.elements[self.array.position(self.index)];
+        self.index = next_in_order(self.index, self.array.len);
+        Some(element)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::VebArray;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn layout() {
+        let sorted: Vec<u64> = (0 .. 15).collect();
+        let array = VebArray::from_sorted(&sorted);
+        let positions: Vec<_> =
+            (1 .. 16).map(|index| array.position(index)).collect();
+        assert_eq!(
+            positions,
+            [0, 1, 2, 3, 6, 9, 12, 4, 5, 7, 8, 10, 11, 13, 14]
+        );
+    }
+
+    #[test]
+    fn same_as_sorted() {
+        let mut rng = StdRng::seed_from_u64(13);
+        for len in [0, 1, 2, 7, 8, 1000] {
+            let mut sorted: Vec<u64> =
+                (0 .. len).map(|_| rng.gen_range(0 .. 300)).collect();
+            sorted.sort();
+            let mut array = VebArray::from_sorted(&sorted);
+            assert!(array.into_iter().eq(sorted.iter().copied()));
+
+            for _ in 0 .. 20 {
+                let target = rng.gen_range(0 .. 350);
+                array.inc_less_than(target);
+                for element in &mut sorted {
+                    *element += (*element < target) as u64;
+                }
+                assert!(array.into_iter().eq(sorted.iter().copied()));
+                for element in 0 .. 350 {
+                    assert_eq!(
+                        array.find(element),
+                        sorted.binary_search(&element).is_ok()
+                    );
+                }
+            }
+        }
+    }
+}
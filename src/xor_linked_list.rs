@@ -0,0 +1,308 @@
+use crate::{
+    element::Element,
+    pool::{NodeBox, NodePtr, Pool},
+    residency::PageSet,
+    trace,
+};
+use std::{alloc::Layout, marker::PhantomData};
+
+type Link<E> = Option<NodePtr<Node<E>>>;
+
+// The address of the previous node xor the address of the next one, an
+// absent node having address 0.
+#[derive(Debug)]
+struct Node<E> {
+    data: E,
+    link: usize,
+}
+
+fn address<E>(link: Link<E>) -> usize {
+    link.map_or(0, NodePtr::address)
+}
+
+// The node after `node` coming from `from`, in either direction.
+fn step<E>(from: Link<E>, node: NodePtr<Node<E>>) -> Link<E> {
+    let link = unsafe { node.as_ref() }.link;
+    NodePtr::from_address(link ^ address(from))
+}
+
+// Doubly linked list keeping a single link word per node.
+#[derive(Debug)]
+pub struct XorLinkedList<E> {
+    head: Link<E>,
+    tail: Link<E>,
+    len: usize,
+}
+
+impl<E> Default for XorLinkedList<E>
+where
+    E: Element,
+{
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<E> XorLinkedList<E>
+where
+    E: Element,
+{
+    pub fn empty() -> Self {
+        Self { head: None, tail: None, len: 0 }
+    }
+
+    pub fn node_layout() -> Layout {
+        Layout::new::<Node<E>>()
+    }
+
+    pub fn push_front(&mut self, element: E) {
+        self.push_front_with(element, NodeBox::new);
+    }
+
+    // The pool must outlive the list.
+    pub unsafe fn push_front_in(&mut self, element: E, pool: &mut Pool) {
+        self.push_front_with(element, |node| pool.alloc(node));
+    }
+
+    fn push_front_with<F>(&mut self, element: E, alloc: F)
+    where
+        F: FnOnce(Node<E>) -> NodeBox<Node<E>>,
+    {
+        let node = Node { data: element, link: address(self.head) };
+        let node = alloc(node).into_node_ptr();
+        match self.head {
+            Some(head) => unsafe {
+                (*head.as_ptr()).link ^= address(Some(node));
+            },
+            None => self.tail = Some(node),
+        }
+        self.head = Some(node);
+        self.len += 1;
+        trace::touch(unsafe { node.as_ref() });
+    }
+
+    pub fn push_back(&mut self, element: E) {
+        self.push_back_with(element, NodeBox::new);
+    }
+
+    // The pool must outlive the list.
+    pub unsafe fn push_back_in(&mut self, element: E, pool: &mut Pool) {
+        self.push_back_with(element, |node| pool.alloc(node));
+    }
+
+    fn push_back_with<F>(&mut self, element: E, alloc: F)
+    where
+        F: FnOnce(Node<E>) -> NodeBox<Node<E>>,
+    {
+        let node = Node { data: element, link: address(self.tail) };
+        let node = alloc(node).into_node_ptr();
+        match self.tail {
+            Some(tail) => unsafe {
+                (*tail.as_ptr()).link ^= address(Some(node));
+            },
+            None => self.head = Some(node),
+        }
+        self.tail = Some(node);
+        self.len += 1;
+        trace::touch(unsafe { node.as_ref() });
+    }
+
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, E> {
+        CursorMut { prev: None, current: self.head, list: self }
+    }
+
+    pub fn find(&self, element: E) -> bool {
+        let mut prev = None;
+        let mut link = self.head;
+        while let Some(node) = link {
+            trace::touch(unsafe { node.as_ref() });
+            if unsafe { node.as_ref() }.data == element {
+                return true;
+            }
+            link = step(prev, node);
+            prev = Some(node);
+        }
+        false
+    }
+
+    pub fn inc_less_than(&mut self, element: E) {
+        let mut prev = None;
+        let mut link = self.head;
+        while let Some(node) = link {
+            let data = unsafe { &mut (*node.as_ptr()).data };
+            trace::touch(unsafe { node.as_ref() });
+            if *data < element {
+                *data = data.wrapping_inc();
+            }
+            link = step(prev, node);
+            prev = Some(node);
+        }
+    }
+
+    pub fn storage(&self, pages: &mut PageSet) {
+        let mut prev = None;
+        let mut link = self.head;
+        while let Some(node) = link {
+            pages.insert(unsafe { node.as_ref() });
+            link = step(prev, node);
+            prev = Some(node);
+        }
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        let mut prev = None;
+        let mut link = self.head;
+        let mut bytes = 0;
+        while let Some(node) = link {
+            bytes += node.allocated_size();
+            link = step(prev, node);
+            prev = Some(node);
+        }
+        bytes
+    }
+}
+
+impl<E> Clone for XorLinkedList<E>
+where
+    E: Element,
+{
+    fn clone(&self) -> Self {
+        let mut new_list = Self::empty();
+        for element in self {
+            new_list.push_back(element);
+        }
+        new_list
+    }
+}
+
+impl<E> Drop for XorLinkedList<E> {
+    fn drop(&mut self) {
+        let mut prev = None;
+        let mut link = self.head;
+        while let Some(node) = link {
+            link = step(prev, node);
+            prev = Some(node);
+            drop(unsafe { node.into_box() });
+        }
+    }
+}
+
+// Points to a node of the list, or past its end, remembering the node before
+// it to find its neighbours.
+#[derive(Debug)]
+pub struct CursorMut<'list, E> {
+    list: &'list mut XorLinkedList<E>,
+    prev: Link<E>,
+    current: Link<E>,
+}
+
+impl<'list, E> CursorMut<'list, E>
+where
+    E: Element,
+{
+    pub fn current(&self) -> Option<E> {
+        self.current.map(|node| unsafe { node.as_ref() }.data)
+    }
+
+    pub fn move_next(&mut self) {
+        if let Some(node) = self.current {
+            self.current = step(self.prev, node);
+            self.prev = Some(node);
+        }
+    }
+
+    // Unlinks the current node and moves to the next one.
+    pub fn remove_current(&mut self) -> Option<E> {
+        let node = self.current?;
+        let next = step(self.prev, node);
+        // Each neighbour swaps the node for the other neighbour.
+        let node_address = address(Some(node));
+        match self.prev {
+            Some(prev) => unsafe {
+                (*prev.as_ptr()).link ^= node_address ^ address(next);
+            },
+            None => self.list.head = next,
+        }
+        match next {
+            Some(next) => unsafe {
+                (*next.as_ptr()).link ^= node_address ^ address(self.prev);
+            },
+            None => self.list.tail = self.prev,
+        }
+        self.list.len -= 1;
+        self.current = next;
+        let node = unsafe { node.into_box() };
+        Some(node.data)
+    }
+}
+
+impl<'list, E> IntoIterator for &'list XorLinkedList<E>
+where
+    E: Element,
+{
+    type Item = E;
+    type IntoIter = Iter<'list, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter {
+            before_front: None,
+            front: self.head,
+            after_back: None,
+            back: self.tail,
+            len: self.len,
+            _list: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Iter<'list, E> {
+    before_front: Link<E>,
+    front: Link<E>,
+    after_back: Link<E>,
+    back: Link<E>,
+    // Nodes between the front and the back, both included.
+    len: usize,
+    _list: PhantomData<&'list XorLinkedList<E>>,
+}
+
+impl<'list, E> Iterator for Iter<'list, E>
+where
+    E: Element,
+{
+    type Item = E;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = self.front?;
+        self.front = step(self.before_front, node);
+        self.before_front = Some(node);
+        self.len -= 1;
+        Some(unsafe { node.as_ref() }.data)
+    }
+}
+
+impl<'list, E> DoubleEndedIterator for Iter<'list, E>
+where
+    E: Element,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = self.back?;
+        self.back = step(self.after_back, node);
+        self.after_back = Some(node);
+        self.len -= 1;
+        Some(unsafe { node.as_ref() }.data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::XorLinkedList;
+
+    crate::doubly_linked_list::doubly_linked_tests!(XorLinkedList);
+}